@@ -0,0 +1,335 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::hint::black_box;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use bit_vec::BitVec;
+use std::time::{Duration, Instant};
+
+use ecs::*;
+
+const BULLETS: usize = 10_000;
+const TANKS: usize = 10_000;
+const ROUNDS: u32 = 50;
+const BATCHES: u32 = 10;
+
+#[derive(Component, Clone, Copy)]
+struct Position(f32, f32);
+
+#[derive(Component, Clone, Copy)]
+struct Velocity(f32, f32);
+
+#[derive(Component, Clone, Copy)]
+struct Lifetime(f32);
+
+#[derive(Component, Clone, Copy)]
+struct Tank;
+
+#[derive(Component, Clone, Copy)]
+struct Bullet;
+
+// The storage from before archetype tables: one vector per type indexed by
+// entity, shared through `Rc<RefCell<..>>`, plus a bit per live entity.
+trait Indexed: 'static {
+    const INDEX: usize;
+}
+
+impl Indexed for Position {
+    const INDEX: usize = 0;
+}
+
+impl Indexed for Velocity {
+    const INDEX: usize = 1;
+}
+
+impl Indexed for Lifetime {
+    const INDEX: usize = 2;
+}
+
+impl Indexed for Tank {
+    const INDEX: usize = 3;
+}
+
+impl Indexed for Bullet {
+    const INDEX: usize = 4;
+}
+
+type Container<T> = Rc<RefCell<Vec<Option<T>>>>;
+
+struct Baseline {
+    components: Vec<Option<Box<dyn Any>>>,
+    alive: RefCell<BitVec>,
+}
+
+impl Baseline {
+    fn new() -> Self {
+        Self {
+            components: (0..5).map(|_| None).collect(),
+            alive: RefCell::new(BitVec::new()),
+        }
+    }
+
+    fn spawn(&mut self) -> usize {
+        let mut alive = self.alive.borrow_mut();
+        alive.push(true);
+        alive.len() - 1
+    }
+
+    fn add<T: Indexed>(&mut self, index: usize, component: T) {
+        let slot = &mut self.components[T::INDEX];
+        let container = slot.get_or_insert_with(|| Box::new(Container::<T>::default()));
+        let mut container = container.downcast_ref::<Container<T>>().unwrap().borrow_mut();
+        if container.len() <= index {
+            container.resize_with(index + 1, || None);
+        }
+        container[index] = Some(component);
+    }
+
+    fn container<T: Indexed>(&self) -> Container<T> {
+        self.components[T::INDEX]
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<Container<T>>()
+            .unwrap()
+            .clone()
+    }
+
+    fn visit<T: Indexed>(&self, index: usize, f: impl FnOnce(&mut Option<T>)) {
+        if !self.alive.borrow().get(index).unwrap() {
+            return;
+        }
+
+        let container = self.container::<T>();
+        let mut container = container.borrow_mut();
+        if let Some(component) = container.get_mut(index) {
+            f(component);
+        }
+    }
+
+    fn visit2<A: Indexed, B: Indexed>(&self, index: usize, f: impl FnOnce(&mut Option<A>, &mut Option<B>)) {
+        if !self.alive.borrow().get(index).unwrap() {
+            return;
+        }
+
+        let (a, b) = (self.container::<A>(), self.container::<B>());
+        let (mut a, mut b) = (a.borrow_mut(), b.borrow_mut());
+        if let (Some(a), Some(b)) = (a.get_mut(index), b.get_mut(index)) {
+            f(a, b);
+        }
+    }
+
+    fn visit_all<T: Indexed>(&self, mut f: impl FnMut(&mut T)) {
+        let container = self.container::<T>();
+        let mut container = container.borrow_mut();
+        let alive = self.alive.borrow().clone();
+        for (index, component) in container.iter_mut().enumerate() {
+            if let (true, Some(component)) = (alive.get(index).unwrap(), component) {
+                f(component);
+            }
+        }
+    }
+
+    fn visit_all2<A: Indexed, B: Indexed>(&self, mut f: impl FnMut(&mut A, &mut B)) {
+        let (a, b) = (self.container::<A>(), self.container::<B>());
+        let (mut a, mut b) = (a.borrow_mut(), b.borrow_mut());
+        let alive = self.alive.borrow().clone();
+        for (index, (a, b)) in a.iter_mut().zip(b.iter_mut()).enumerate() {
+            if let (true, Some(a), Some(b)) = (alive.get(index).unwrap(), a, b) {
+                f(a, b);
+            }
+        }
+    }
+}
+
+// Same entities as `build_world`, in the same order.
+fn build_baseline() -> (Baseline, Vec<usize>) {
+    let mut world = Baseline::new();
+    let mut bullets = Vec::with_capacity(BULLETS);
+    for i in 0..BULLETS.max(TANKS) {
+        if i < TANKS {
+            let tank = world.spawn();
+            world.add(tank, Position(i as f32, 0.));
+            world.add(tank, Tank);
+        }
+        if i < BULLETS {
+            let bullet = world.spawn();
+            world.add(bullet, Position(0., i as f32));
+            world.add(bullet, Velocity(1., 1.));
+            world.add(bullet, Lifetime(3.));
+            world.add(bullet, Bullet);
+            bullets.push(bullet);
+        }
+    }
+
+    (world, bullets)
+}
+
+// `storage` picks tables or sparse sets for everything but the bullet tag.
+fn build_world(storage: StorageType) -> (EcsRc, Vec<EntityId>) {
+    let mut world = Ecs::new();
+    world.register_component_storage::<Position>(storage);
+    world.register_component_storage::<Velocity>(storage);
+    world.register_component_storage::<Lifetime>(storage);
+    world.register_component_storage::<Tank>(storage);
+    world.register_component_storage::<Bullet>(StorageType::SparseSet);
+
+    // Tanks and bullets interleaved, so the slot order of a per entity
+    // lookup is scattered over both kinds.
+    let mut bullets = Vec::with_capacity(BULLETS);
+    for i in 0..BULLETS.max(TANKS) {
+        if i < TANKS {
+            world.spawn((Position(i as f32, 0.), Tank));
+        }
+        if i < BULLETS {
+            let bullet = world.spawn((Position(0., i as f32), Velocity(1., 1.), Lifetime(3.), Bullet));
+            bullets.push(bullet);
+        }
+    }
+
+    (Arc::new(EcsLock::new(world)), bullets)
+}
+
+// Best of several batches, so a busy machine skews the numbers less.
+fn measure(name: &str, entities: usize, mut f: impl FnMut()) -> f64 {
+    f();
+
+    let mut best = Duration::MAX;
+    for _ in 0..BATCHES {
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            f();
+        }
+        best = best.min(start.elapsed() / ROUNDS);
+    }
+    let per_entity = best.as_nanos() as f64 / entities as f64;
+
+    println!("{:<24} {:>10.2?} per round {:>8.2} ns per entity", name, best, per_entity);
+    per_entity
+}
+
+fn run(storage: StorageType) -> Vec<(&'static str, f64)> {
+    let (world, bullets) = build_world(storage);
+    let ecs = world.borrow();
+    let mut results = Vec::new();
+
+    let name = "visit per entity";
+    results.push((name, measure(name, BULLETS, || {
+        for id in bullets.iter() {
+            let mut velocity = None;
+            ecs.visit::<Velocity>(*id, |v| velocity = *v);
+            if let Some(velocity) = velocity {
+                ecs.visit::<Position>(*id, |p| {
+                    if let Some(p) = p {
+                        p.0 += velocity.0;
+                        p.1 += velocity.1;
+                    }
+                });
+            }
+        }
+    })));
+
+    let name = "query get per entity";
+    results.push((name, measure(name, BULLETS, || {
+        let mut query = ecs.query::<(&mut Position, &Velocity)>();
+        for id in bullets.iter() {
+            if let Some((mut p, v)) = query.get(*id) {
+                p.0 += v.0;
+                p.1 += v.1;
+            }
+        }
+    })));
+
+    let name = "query rows";
+    results.push((name, measure(name, BULLETS, || {
+        for (mut p, v) in ecs.query::<(&mut Position, &Velocity)>().iter_mut() {
+            p.0 += v.0;
+            p.1 += v.1;
+        }
+    })));
+
+    let name = "query sparse tag";
+    results.push((name, measure(name, BULLETS, || {
+        let mut query = ecs.query_filtered::<&Lifetime, With<Bullet>>();
+        black_box(query.iter_mut().map(|l| l.0).sum::<f32>());
+    })));
+
+    let name = "query all positions";
+    results.push((name, measure(name, BULLETS + TANKS, || {
+        black_box(ecs.query::<&Position>().iter_mut().map(|p| p.0 + p.1).sum::<f32>());
+    })));
+
+    results
+}
+
+// The closest the old API came to each case, it had no queries or filters.
+fn run_baseline() -> Vec<(&'static str, f64)> {
+    let (world, bullets) = build_baseline();
+    let mut results = Vec::new();
+
+    let name = "visit per entity";
+    results.push((name, measure(name, BULLETS, || {
+        for &index in bullets.iter() {
+            let mut velocity = None;
+            world.visit::<Velocity>(index, |v| velocity = *v);
+            if let Some(velocity) = velocity {
+                world.visit::<Position>(index, |p| {
+                    if let Some(p) = p {
+                        p.0 += velocity.0;
+                        p.1 += velocity.1;
+                    }
+                });
+            }
+        }
+    })));
+
+    let name = "query get per entity";
+    results.push((name, measure(name, BULLETS, || {
+        for &index in bullets.iter() {
+            world.visit2::<Position, Velocity>(index, |p, v| {
+                if let (Some(p), Some(v)) = (p, v) {
+                    p.0 += v.0;
+                    p.1 += v.1;
+                }
+            });
+        }
+    })));
+
+    let name = "query rows";
+    results.push((name, measure(name, BULLETS, || {
+        world.visit_all2::<Position, Velocity>(|p, v| {
+            p.0 += v.0;
+            p.1 += v.1;
+        });
+    })));
+
+    let name = "query sparse tag";
+    results.push((name, measure(name, BULLETS, || {
+        let mut sum = 0.;
+        world.visit_all2::<Lifetime, Bullet>(|l, _| sum += l.0);
+        black_box(sum);
+    })));
+
+    let name = "query all positions";
+    results.push((name, measure(name, BULLETS + TANKS, || {
+        let mut sum = 0.;
+        world.visit_all::<Position>(|p| sum += p.0 + p.1);
+        black_box(sum);
+    })));
+
+    results
+}
+
+fn main() {
+    println!("per type vectors (before)");
+    let before = run_baseline();
+    println!("sparse sets");
+    let sparse = run(StorageType::SparseSet);
+    println!("archetype tables");
+    let tables = run(StorageType::Table);
+
+    println!("{:<24} {:>10} {:>10}", "speedup over before", "sparse", "tables");
+    for ((name, before), ((_, sparse), (_, tables))) in before.iter().zip(sparse.iter().zip(tables.iter())) {
+        println!("{:<24} {:>9.2}x {:>9.2}x", name, before / sparse, before / tables);
+    }
+}
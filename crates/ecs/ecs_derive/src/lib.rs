@@ -2,40 +2,71 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use std::sync::Mutex;
-use syn::{parse, parse::Parser, parse_macro_input, ItemEnum, ItemStruct};
-
-static COUNTER: Mutex<usize> = Mutex::new(0);
-
-#[proc_macro_attribute]
-pub fn component_impl(args: TokenStream, input: TokenStream) -> TokenStream {
-    let mut item_struct = parse_macro_input!(input as ItemStruct);
-    let _ = parse_macro_input!(args as parse::Nothing);
-
-    if let syn::Fields::Named(ref mut fields) = item_struct.fields {
-        fields.named.push(
-            syn::Field::parse_named
-                .parse2(quote! { pub entity: EntityWeak })
-                .unwrap(),
-        );
+use syn::{parse_macro_input, parse_quote, DeriveInput, Generics};
+
+// Storages are type erased through `Any` and shared between threads, so
+// every type parameter has to be 'static, Send and Sync.
+fn add_bounds(generics: &mut Generics) {
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(::std::marker::Send));
+        param.bounds.push(parse_quote!(::std::marker::Sync));
+        param.bounds.push(parse_quote!('static));
     }
+}
+
+#[proc_macro_derive(Component)]
+pub fn derive_component(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    add_bounds(&mut input.generics);
 
-    let name = &item_struct.ident;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
 
-    let mut locked = COUNTER.lock().unwrap();
-    let index: usize = *locked;
-    *locked = index + 1;
+    quote! {
+        impl #impl_generics ::ecs::Component for #name #ty_generics #where_clause {}
+    }
+    .into()
+}
 
-    return quote! {
-        #item_struct
+#[proc_macro_derive(Bundle)]
+pub fn derive_bundle(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+    add_bounds(&mut input.generics);
 
-        impl Component for #name {
-            const INDEX: usize = #index;
+    let fields = match &input.data {
+        syn::Data::Struct(data) => &data.fields,
+        _ => {
+            return syn::Error::new_spanned(&input.ident, "Bundle can only be derived for structs")
+                .to_compile_error()
+                .into();
+        }
+    };
+
+    let members: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => quote! { #ident },
+            None => {
+                let index = syn::Index::from(i);
+                quote! { #index }
+            }
+        })
+        .collect();
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::ecs::Bundle for #name #ty_generics #where_clause {
+            fn component_indexes(&self, ecs: &mut ::ecs::Ecs, indexes: &mut ::std::vec::Vec<usize>) {
+                #(::ecs::Bundle::component_indexes(&self.#members, ecs, indexes);)*
+            }
 
-            fn get_entity_id(&self) -> Option<EntityId> {
-                Some(self.entity.upgrade()?.get_id())
+            fn write(self, ecs: &mut ::ecs::Ecs, entity_id: ::ecs::EntityId) {
+                #(::ecs::Bundle::write(self.#members, ecs, entity_id);)*
             }
         }
     }
-    .into();
+    .into()
 }
@@ -0,0 +1,88 @@
+use std::any::TypeId;
+use std::collections::HashSet;
+
+use crate::{Component, QueryData, QueryFilter};
+
+// Components and resources a system reads and writes. Systems whose accesses
+// do not conflict may run at the same time.
+#[derive(Clone, Debug, Default)]
+pub struct Access {
+    reads: HashSet<TypeId>,
+    writes: HashSet<TypeId>,
+    // Types added again while one of the borrows writes them, fine across
+    // queries but not within one.
+    aliased: Vec<&'static str>,
+}
+
+impl Access {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn query<D: QueryData>(self) -> Self {
+        self.query_filtered::<D, ()>()
+    }
+
+    pub fn query_filtered<D: QueryData, F: QueryFilter>(mut self) -> Self {
+        D::access(&mut self);
+        F::access(&mut self);
+        self
+    }
+
+    pub fn read<T: Component>(mut self) -> Self {
+        self.add_read::<T>();
+        self
+    }
+
+    pub fn write<T: Component>(mut self) -> Self {
+        self.add_write::<T>();
+        self
+    }
+
+    pub fn resource<R: Send + Sync + 'static>(mut self) -> Self {
+        self.add_read::<R>();
+        self
+    }
+
+    pub fn resource_mut<R: Send + Sync + 'static>(mut self) -> Self {
+        self.add_write::<R>();
+        self
+    }
+
+    pub fn add_read<T: 'static>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self.writes.contains(&id) {
+            self.aliased.push(std::any::type_name::<T>());
+        }
+        self.reads.insert(id);
+    }
+
+    pub fn add_write<T: 'static>(&mut self) {
+        let id = TypeId::of::<T>();
+        if self.reads.contains(&id) || self.writes.contains(&id) {
+            self.aliased.push(std::any::type_name::<T>());
+        }
+        self.writes.insert(id);
+    }
+
+    pub fn aliased(&self) -> &[&'static str] {
+        &self.aliased
+    }
+
+    // Either side writes something the other one reads or writes.
+    pub fn conflicts_with(&self, other: &Access) -> bool {
+        let touches = |access: &Access, id: &TypeId| access.reads.contains(id) || access.writes.contains(id);
+
+        self.writes.iter().any(|id| touches(other, id)) || other.writes.iter().any(|id| touches(self, id))
+    }
+
+    // Everything `other` reads is read or written here, and everything it
+    // writes is written here.
+    pub fn covers(&self, other: &Access) -> bool {
+        other.writes.is_subset(&self.writes)
+            && other
+                .reads
+                .iter()
+                .all(|id| self.reads.contains(id) || self.writes.contains(id))
+    }
+}
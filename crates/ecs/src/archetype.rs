@@ -0,0 +1,346 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::storage::{read, write};
+use crate::Column;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntityLocation {
+    pub archetype: usize,
+    pub row: usize,
+}
+
+pub(crate) trait AnyColumn: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn empty(&self) -> Box<dyn AnyColumn>;
+    fn swap_remove(&self, row: usize);
+    fn move_row(&self, row: usize, dst: &dyn AnyColumn);
+    fn push_empty(&self);
+    fn reserve(&self, additional: usize);
+}
+
+pub(crate) fn new_column<T: 'static + Send + Sync>() -> Box<dyn AnyColumn> {
+    Box::new(Column::<T>::new())
+}
+
+impl<T: 'static + Send + Sync> AnyColumn for Column<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn empty(&self) -> Box<dyn AnyColumn> {
+        Box::new(Column::<T>::new())
+    }
+
+    fn swap_remove(&self, row: usize) {
+        Column::swap_remove(self, row);
+    }
+
+    fn move_row(&self, row: usize, dst: &dyn AnyColumn) {
+        let dst = dst.as_any().downcast_ref::<Column<T>>().unwrap();
+        Column::move_row(self, row, dst);
+    }
+
+    fn push_empty(&self) {
+        Column::push_empty(self);
+    }
+
+    fn reserve(&self, additional: usize) {
+        Column::reserve(self, additional);
+    }
+}
+
+const ENTITIES: &str = "archetype row list";
+
+// Entities with the same set of table components, one column per component
+// and one row per entity.
+pub struct Archetype {
+    id: usize,
+    components: Vec<usize>,
+    columns: Vec<Box<dyn AnyColumn>>,
+    entities: RwLock<Vec<u32>>,
+}
+
+impl Archetype {
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    // Sorted component indexes.
+    pub fn components(&self) -> &[usize] {
+        &self.components
+    }
+
+    pub fn contains(&self, component: usize) -> bool {
+        self.components.binary_search(&component).is_ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entities().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn entities(&self) -> RwLockReadGuard<'_, Vec<u32>> {
+        read(&self.entities, ENTITIES)
+    }
+
+    fn entities_mut(&self) -> RwLockWriteGuard<'_, Vec<u32>> {
+        write(&self.entities, ENTITIES)
+    }
+
+    pub fn column<T: 'static>(&self, component: usize) -> Option<&Column<T>> {
+        let index = self.components.binary_search(&component).ok()?;
+
+        self.columns[index].as_any().downcast_ref::<Column<T>>()
+    }
+
+    fn column_at(&self, component: usize) -> Option<&dyn AnyColumn> {
+        let index = self.components.binary_search(&component).ok()?;
+
+        Some(self.columns[index].as_ref())
+    }
+}
+
+// Archetypes are only ever added, so references to them stay valid for as
+// long as the world does.
+#[derive(Default)]
+pub struct Archetypes {
+    // Boxed so growing the list never moves an archetype.
+    #[allow(clippy::vec_box)]
+    archetypes: RwLock<Vec<Box<Archetype>>>,
+    index: RwLock<HashMap<Vec<usize>, usize>>,
+    locations: RwLock<Vec<Option<EntityLocation>>>,
+}
+
+impl Archetypes {
+    pub fn len(&self) -> usize {
+        self.archetypes.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn get(&self, id: usize) -> Option<&Archetype> {
+        let archetypes = self.archetypes.read().unwrap();
+        let archetype: *const Archetype = archetypes.get(id)?.as_ref();
+
+        // The archetype is boxed and never removed, so it outlives the borrow
+        // of the list.
+        Some(unsafe { &*archetype })
+    }
+
+    // `None` for entities without table components.
+    #[inline]
+    pub fn location(&self, index: usize) -> Option<EntityLocation> {
+        self.locations.read().unwrap().get(index).copied().flatten()
+    }
+
+    fn set_location(&self, index: usize, location: Option<EntityLocation>) {
+        let mut locations = self.locations.write().unwrap();
+        if locations.len() <= index {
+            locations.resize(index + 1, None);
+        }
+        locations[index] = location;
+    }
+
+    fn find_or_create(&self, components: Vec<usize>, columns: impl FnOnce() -> Vec<Box<dyn AnyColumn>>) -> usize {
+        let mut index = self.index.write().unwrap();
+        if let Some(&id) = index.get(&components) {
+            return id;
+        }
+
+        let mut archetypes = self.archetypes.write().unwrap();
+        let id = archetypes.len();
+        archetypes.push(Box::new(Archetype {
+            id,
+            components: components.clone(),
+            columns: columns(),
+            entities: RwLock::new(Vec::new()),
+        }));
+        index.insert(components, id);
+
+        id
+    }
+
+    // Columns the destination shares with the source take the row along,
+    // the rest drop it.
+    fn move_entity(&self, index: usize, location: EntityLocation, dst: Option<usize>) {
+        let src = self.get(location.archetype).unwrap();
+        let dst = dst.and_then(|dst| self.get(dst));
+
+        for (component, column) in src.components.iter().zip(src.columns.iter()) {
+            match dst.and_then(|dst| dst.column_at(*component)) {
+                Some(dst_column) => column.move_row(location.row, dst_column),
+                None => column.swap_remove(location.row),
+            }
+        }
+
+        let mut entities = src.entities_mut();
+        entities.swap_remove(location.row);
+        if let Some(&moved) = entities.get(location.row) {
+            self.set_location(moved as usize, Some(location));
+        }
+        drop(entities);
+
+        let location = dst.map(|dst| {
+            let mut entities = dst.entities_mut();
+            entities.push(index as u32);
+            EntityLocation {
+                archetype: dst.id,
+                row: entities.len() - 1,
+            }
+        });
+        self.set_location(index, location);
+    }
+
+    // Archetype of the entity with `components` added, `None` when it ends
+    // up without table components.
+    fn target(
+        &self,
+        src: Option<&Archetype>,
+        components: &[usize],
+        new_column: impl Fn(usize) -> Box<dyn AnyColumn>,
+    ) -> Option<usize> {
+        let mut target = src.map(|src| src.components.clone()).unwrap_or_default();
+        for &component in components {
+            if let Err(position) = target.binary_search(&component) {
+                target.insert(position, component);
+            }
+        }
+        if let Some(src) = src.filter(|src| src.components == target) {
+            return Some(src.id);
+        }
+        if target.is_empty() {
+            return None;
+        }
+
+        Some(self.find_or_create(target.clone(), || {
+            target
+                .iter()
+                .map(|&component| match src.and_then(|src| src.column_at(component)) {
+                    Some(column) => column.empty(),
+                    None => new_column(component),
+                })
+                .collect()
+        }))
+    }
+
+    // Moves the entity to the archetype that also has `components` in one
+    // step. Their rows start empty and are filled by `insert`.
+    pub(crate) fn reserve(&self, index: usize, components: &[usize], new_column: impl Fn(usize) -> Box<dyn AnyColumn>) {
+        let location = self.location(index);
+        let src = location.and_then(|location| self.get(location.archetype));
+        let Some(dst) = self.target(src, components, new_column) else {
+            return;
+        };
+        if src.is_some_and(|src| src.id == dst) {
+            return;
+        }
+
+        match location {
+            Some(location) => self.move_entity(index, location, Some(dst)),
+            None => {
+                let archetype = self.get(dst).unwrap();
+                let mut entities = archetype.entities_mut();
+                entities.push(index as u32);
+                let row = entities.len() - 1;
+                drop(entities);
+                self.set_location(index, Some(EntityLocation { archetype: dst, row }));
+            }
+        }
+
+        let dst = self.get(dst).unwrap();
+        for (component, column) in dst.components.iter().zip(dst.columns.iter()) {
+            if !src.is_some_and(|src| src.contains(*component)) {
+                column.push_empty();
+            }
+        }
+    }
+
+    // Room for `additional` new entities with exactly `components`.
+    pub(crate) fn reserve_rows(
+        &self,
+        components: &[usize],
+        additional: usize,
+        new_column: impl Fn(usize) -> Box<dyn AnyColumn>,
+    ) {
+        if let Some(dst) = self.target(None, components, new_column) {
+            let dst = self.get(dst).unwrap();
+            dst.entities_mut().reserve(additional);
+            for column in dst.columns.iter() {
+                column.reserve(additional);
+            }
+        }
+    }
+
+    // Returns true when the component was not there before.
+    pub(crate) fn insert<T: Send + Sync + 'static>(&self, component_index: usize, index: usize, component: T, tick: u32) -> bool {
+        let is_added = !self
+            .location(index)
+            .and_then(|location| self.get(location.archetype))
+            .is_some_and(|archetype| archetype.contains(component_index));
+        self.reserve(index, &[component_index], |_| new_column::<T>());
+
+        let location = self.location(index).unwrap();
+        let archetype = self.get(location.archetype).unwrap();
+        archetype.column::<T>(component_index).unwrap().replace(location.row, component, tick);
+
+        is_added
+    }
+
+    pub(crate) fn remove<T: 'static>(&self, component_index: usize, index: usize) -> Option<T> {
+        let location = self.location(index)?;
+        let src = self.get(location.archetype)?;
+        let component = src.column::<T>(component_index)?.take(location.row);
+
+        self.remove_column(component_index, index);
+
+        component
+    }
+
+    // Moves the entity to the archetype without the component, dropping it.
+    pub(crate) fn remove_column(&self, component_index: usize, index: usize) {
+        let Some(location) = self.location(index) else {
+            return;
+        };
+        let src = self.get(location.archetype).unwrap();
+        if !src.contains(component_index) {
+            return;
+        }
+
+        let components: Vec<usize> = src
+            .components
+            .iter()
+            .copied()
+            .filter(|component| *component != component_index)
+            .collect();
+        let dst = match components.is_empty() {
+            true => None,
+            false => Some(self.find_or_create(components, || {
+                src.components
+                    .iter()
+                    .zip(src.columns.iter())
+                    .filter(|(component, _)| **component != component_index)
+                    .map(|(_, column)| column.empty())
+                    .collect()
+            })),
+        };
+
+        self.move_entity(index, location, dst);
+    }
+
+    // Drops every table component of the entity.
+    pub(crate) fn remove_entity(&self, index: usize) {
+        if let Some(location) = self.location(index) {
+            self.move_entity(index, location, None);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Component, Ecs, EntityId};
+
+// Group of components inserted together with a single world borrow. The
+// entity moves to the archetype of the whole bundle once, then every
+// component is written into the row made for it.
+pub trait Bundle: Send + 'static {
+    // Registers the components of the bundle and lists their indexes.
+    fn component_indexes(&self, ecs: &mut Ecs, indexes: &mut Vec<usize>);
+
+    // Writes the components without running hooks, `insert_bundle` runs
+    // them once everything is in place.
+    fn write(self, ecs: &mut Ecs, entity_id: EntityId);
+
+    fn insert_into(self, ecs: &mut Ecs, entity_id: EntityId)
+    where
+        Self: Sized,
+    {
+        ecs.insert_bundle(entity_id, self);
+    }
+}
+
+impl<T: Component> Bundle for T {
+    fn component_indexes(&self, ecs: &mut Ecs, indexes: &mut Vec<usize>) {
+        indexes.push(ecs.register_component::<T>());
+    }
+
+    fn write(self, ecs: &mut Ecs, entity_id: EntityId) {
+        ecs.write_component(entity_id, self);
+    }
+}
+
+impl Bundle for () {
+    fn component_indexes(&self, _ecs: &mut Ecs, _indexes: &mut Vec<usize>) {}
+
+    fn write(self, _ecs: &mut Ecs, _entity_id: EntityId) {}
+}
+
+// Object safe side of `Bundle`, for bundles only known at runtime.
+trait BoxedBundle: Send {
+    fn component_indexes(&self, ecs: &mut Ecs, indexes: &mut Vec<usize>);
+
+    fn write(self: Box<Self>, ecs: &mut Ecs, entity_id: EntityId);
+}
+
+impl<B: Bundle> BoxedBundle for B {
+    fn component_indexes(&self, ecs: &mut Ecs, indexes: &mut Vec<usize>) {
+        Bundle::component_indexes(self, ecs, indexes);
+    }
+
+    fn write(self: Box<Self>, ecs: &mut Ecs, entity_id: EntityId) {
+        Bundle::write(*self, ecs, entity_id);
+    }
+}
+
+// Bundle put together at runtime, e.g. from a prefab file.
+#[derive(Default)]
+pub struct DynamicBundle {
+    parts: Vec<Box<dyn BoxedBundle>>,
+}
+
+impl DynamicBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, bundle: impl Bundle) {
+        self.parts.push(Box::new(bundle));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+}
+
+impl Bundle for DynamicBundle {
+    fn component_indexes(&self, ecs: &mut Ecs, indexes: &mut Vec<usize>) {
+        for part in self.parts.iter() {
+            part.component_indexes(ecs, indexes);
+        }
+    }
+
+    fn write(self, ecs: &mut Ecs, entity_id: EntityId) {
+        for part in self.parts {
+            part.write(ecs, entity_id);
+        }
+    }
+}
+
+macro_rules! impl_bundle_tuple {
+    ($($name: ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: Bundle),*> Bundle for ($($name,)*) {
+            fn component_indexes(&self, ecs: &mut Ecs, indexes: &mut Vec<usize>) {
+                let ($($name,)*) = self;
+                $($name.component_indexes(ecs, indexes);)*
+            }
+
+            fn write(self, ecs: &mut Ecs, entity_id: EntityId) {
+                let ($($name,)*) = self;
+                $($name.write(ecs, entity_id);)*
+            }
+        }
+    };
+}
+
+impl_bundle_tuple!(A);
+impl_bundle_tuple!(A, B);
+impl_bundle_tuple!(A, B, C);
+impl_bundle_tuple!(A, B, C, D);
+impl_bundle_tuple!(A, B, C, D, E);
+impl_bundle_tuple!(A, B, C, D, E, F);
+impl_bundle_tuple!(A, B, C, D, E, F, G);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_bundle_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
@@ -0,0 +1,89 @@
+use std::ops::Deref;
+
+use crate::{Bundle, Component, Ecs, EcsRc, Entity, EntityId};
+
+type Command = Box<dyn FnOnce(&EcsRc) + Send>;
+
+#[derive(Default)]
+pub struct Commands {
+    queue: Vec<Command>,
+}
+
+impl Commands {
+    pub fn new() -> Self {
+        Self { queue: Vec::new() }
+    }
+
+    pub fn add(&mut self, command: impl FnOnce(&EcsRc) + Send + 'static) {
+        self.queue.push(Box::new(command));
+    }
+
+    pub fn spawn(&mut self, builder: impl FnOnce(&mut Ecs, Entity) + Send + 'static) {
+        self.add(move |world| {
+            let mut ecs = world.deref().borrow_mut();
+            let entity = ecs.spawn_empty();
+            builder(&mut ecs, entity);
+        });
+    }
+
+    pub fn spawn_bundle(&mut self, bundle: impl Bundle) {
+        self.add(move |world| {
+            world.deref().borrow_mut().spawn(bundle);
+        });
+    }
+
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: Vec<B>) {
+        self.add(move |world| {
+            world.deref().borrow_mut().spawn_batch(bundles);
+        });
+    }
+
+    pub fn spawn_pooled<P: 'static>(&mut self, bundle: impl Bundle) {
+        self.add(move |world| {
+            world.deref().borrow_mut().spawn_pooled::<P>(bundle);
+        });
+    }
+
+    pub fn despawn(&mut self, entity_id: EntityId) {
+        self.add(move |world| world.deref().borrow().remove_entity(entity_id));
+    }
+
+    pub fn despawn_batch(&mut self, entity_ids: Vec<EntityId>) {
+        self.add(move |world| world.deref().borrow().despawn_batch(entity_ids));
+    }
+
+    pub fn despawn_pooled<P: 'static>(&mut self, entity_id: EntityId) {
+        self.add(move |world| {
+            world.deref().borrow().despawn_pooled::<P>(entity_id);
+        });
+    }
+
+    pub fn insert<T: 'static + Component>(&mut self, entity_id: EntityId, component: T) {
+        self.add(move |world| {
+            world
+                .deref()
+                .borrow_mut()
+                .insert_component(entity_id, component)
+        });
+    }
+
+    pub fn insert_bundle(&mut self, entity_id: EntityId, bundle: impl Bundle) {
+        self.add(move |world| world.deref().borrow_mut().insert_bundle(entity_id, bundle));
+    }
+
+    pub fn remove<T: 'static + Component>(&mut self, entity_id: EntityId) {
+        self.add(move |world| {
+            world.deref().borrow_mut().remove_component::<T>(entity_id);
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn apply(&mut self, world: &EcsRc) {
+        for command in self.queue.drain(..) {
+            command(world);
+        }
+    }
+}
@@ -1,31 +1,138 @@
-use std::rc::{Rc, Weak};
-use std::{any::Any, any::TypeId, collections::HashMap};
+use std::{any::Any, any::TypeId, collections::HashMap, marker::PhantomData};
+
+use crate::EntityId;
 
 pub trait EventListener {
     fn on_event(&mut self);
 }
 
-trait AsAny {
-    fn as_any(&self) -> &dyn Any;
-    fn as_any_mut(&mut self) -> &mut dyn Any;
-    fn clear(&mut self);
+pub trait Listener<E> {
+    fn on_event(&mut self, event: &E);
 }
 
-struct EventStorage<E> {
-    events: Vec<E>,
+// Event addressed to a single entity, listeners of that entity receive it.
+#[derive(Clone, Debug)]
+pub struct EntityEvent<E> {
+    pub entity: EntityId,
+    pub event: E,
 }
 
-impl<E: Sized + 'static + Clone> EventStorage<E> {
-    fn new() -> Self {
-        Self { events: Vec::new() }
+// Double-buffered channel, events survive one full `update` so every reader
+// gets a chance to see them before they are dropped.
+pub struct Events<E> {
+    previous: Vec<E>,
+    current: Vec<E>,
+    event_count: usize,
+}
+
+impl<E> Events<E> {
+    pub fn new() -> Self {
+        Self {
+            previous: Vec::new(),
+            current: Vec::new(),
+            event_count: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: E) {
+        self.current.push(event);
+        self.event_count += 1;
+    }
+
+    pub fn update(&mut self) {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.previous.len() + self.current.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    fn push(&mut self, ev: E) {
-        self.events.push(ev);
+    pub fn clear(&mut self) {
+        self.previous.clear();
+        self.current.clear();
+    }
+
+    pub fn get_reader(&self) -> EventReader<E> {
+        EventReader {
+            last_event_count: self.event_count,
+            _marker: PhantomData,
+        }
+    }
+
+    fn iter_from(&self, event_count: usize) -> impl Iterator<Item = &E> {
+        let oldest = self.event_count - self.len();
+        let skip = event_count.saturating_sub(oldest);
+
+        self.previous.iter().chain(self.current.iter()).skip(skip)
+    }
+}
+
+impl<E> Default for Events<E> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl<E: Sized + 'static + Clone> AsAny for EventStorage<E> {
+// Cursor into an event channel, each reader sees every event once.
+pub struct EventReader<E> {
+    last_event_count: usize,
+    _marker: PhantomData<fn() -> E>,
+}
+
+impl<E: 'static> EventReader<E> {
+    pub fn new() -> Self {
+        Self {
+            last_event_count: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn read<'a>(&mut self, events: &'a EventSystem) -> impl Iterator<Item = &'a E> {
+        let channel = events.get_events::<E>();
+        let start = self.last_event_count;
+        if let Some(channel) = channel {
+            self.last_event_count = channel.event_count;
+        }
+
+        channel.into_iter().flat_map(move |channel| channel.iter_from(start))
+    }
+
+    pub fn len(&self, events: &EventSystem) -> usize {
+        events
+            .get_events::<E>()
+            .map_or(0, |channel| channel.iter_from(self.last_event_count).count())
+    }
+
+    pub fn is_empty(&self, events: &EventSystem) -> bool {
+        self.len(events) == 0
+    }
+
+    pub fn clear(&mut self, events: &EventSystem) {
+        if let Some(channel) = events.get_events::<E>() {
+            self.last_event_count = channel.event_count;
+        }
+    }
+}
+
+impl<E: 'static> Default for EventReader<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+trait EventChannel: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn update(&mut self);
+    fn clear(&mut self);
+}
+
+impl<E: Send + Sync + 'static> EventChannel for Events<E> {
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -34,17 +141,18 @@ impl<E: Sized + 'static + Clone> AsAny for EventStorage<E> {
         self
     }
 
-    fn clear(&mut self) {
-        self.events.clear();
+    fn update(&mut self) {
+        Events::update(self);
     }
-}
 
-pub trait Listener<E> {
-    fn on_event(&mut self, event: E);
+    fn clear(&mut self) {
+        Events::clear(self);
+    }
 }
 
+#[derive(Default)]
 pub struct EventSystem {
-    storages: HashMap<TypeId, Box<dyn AsAny>>,
+    storages: HashMap<TypeId, Box<dyn EventChannel>>,
 }
 
 impl EventSystem {
@@ -54,60 +162,40 @@ impl EventSystem {
         }
     }
 
-    fn get_storage<E: 'static + Sized + Clone>(&self) -> Option<&EventStorage<E>> {
-        let id = TypeId::of::<E>();
+    pub fn get_events<E: 'static>(&self) -> Option<&Events<E>> {
+        let storage = self.storages.get(&TypeId::of::<E>())?;
 
-        if let Some(storage) = self.storages.get(&id) {
-            let any_stor = storage.as_any();
-            return any_stor.downcast_ref::<EventStorage<E>>();
-        }
-        None
+        storage.as_any().downcast_ref::<Events<E>>()
     }
 
-    fn get_storage_mut<E: 'static + Sized + Clone>(&mut self) -> Option<&mut EventStorage<E>> {
-        let id = TypeId::of::<E>();
-
+    pub fn get_events_mut<E: Send + Sync + 'static>(&mut self) -> &mut Events<E> {
         let storage = self
             .storages
-            .entry(id)
-            .or_insert_with(|| Box::new(EventStorage::<E>::new()));
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Events::<E>::new()));
 
-        let any_stor = storage.as_any_mut();
-        any_stor.downcast_mut::<EventStorage<E>>()
+        storage.as_any_mut().downcast_mut::<Events<E>>().unwrap()
     }
 
-    pub fn push_event<E: 'static + Sized + Clone>(&mut self, ev: E) {
-        if let Some(storage) = self.get_storage_mut::<E>() {
-            storage.push(ev);
-        }
+    pub fn push_event<E: Send + Sync + 'static>(&mut self, ev: E) {
+        self.get_events_mut::<E>().send(ev);
     }
 
-    pub fn process_event<E: 'static + Sized + Clone>(&self, listener: &mut dyn Listener<E>) {
-        if let Some(storage) = self.get_storage::<E>() {
-            for ev in storage.events.iter() {
-                listener.on_event(ev.clone());
-            }
-        }
-    }
-
-    pub fn get_events<E: 'static + Sized + Clone>(&self) -> Option<Vec<E>> {
-        if let Some(stor)  = self.get_storage::<E>() {
-            return stor.events.clone().into();
-        }
-        
-        None
-    }
-
-    pub fn clear<E: 'static + Sized + Clone>(&mut self) {
-        let id = TypeId::of::<E>();
-        if let Some(storage) = self.storages.get_mut(&id) {
+    pub fn clear<E: 'static>(&mut self) {
+        if let Some(storage) = self.storages.get_mut(&TypeId::of::<E>()) {
             storage.clear();
         }
     }
 
     pub fn clear_all(&mut self) {
-        for (_, v) in &mut self.storages {
+        for v in self.storages.values_mut() {
             v.clear();
         }
     }
+
+    pub fn update(&mut self) {
+        for v in self.storages.values_mut() {
+            v.update();
+        }
+    }
 }
@@ -0,0 +1,160 @@
+use std::any::TypeId;
+
+use crate::{run_hook, Component, Ecs, EcsEvent, EntityId, StorageType};
+
+// Set through `Ecs::set_parent`, queries can filter on it like on any other
+// component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parent(EntityId);
+
+impl Component for Parent {}
+
+impl Parent {
+    pub fn get(&self) -> EntityId {
+        self.0
+    }
+}
+
+// Kept in step with `Parent` of the children, never empty.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Children(Vec<EntityId>);
+
+impl Component for Children {}
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = EntityId> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn as_slice(&self) -> &[EntityId] {
+        &self.0
+    }
+}
+
+impl Ecs {
+    // Sparse, so links are added and removed without moving table rows.
+    pub(crate) fn register_hierarchy(&mut self) {
+        self.register_component_storage::<Parent>(StorageType::SparseSet);
+        self.register_component_storage::<Children>(StorageType::SparseSet);
+    }
+
+    pub fn set_parent(&self, child: EntityId, parent: EntityId) -> bool {
+        if !self.is_entity_alive(child) || !self.is_entity_alive(parent) {
+            return false;
+        }
+
+        let _guard = self.hierarchy.lock().unwrap();
+        if self.is_ancestor(child, parent) {
+            return false;
+        }
+
+        self.detach(child);
+        self.insert_link(child, Parent(parent));
+        let pushed = match self.get_mut::<Children>(parent) {
+            Some(mut children) => {
+                children.0.push(child);
+                true
+            }
+            None => false,
+        };
+        if !pushed {
+            self.insert_link(parent, Children(vec![child]));
+        }
+
+        true
+    }
+
+    pub fn remove_parent(&self, child: EntityId) -> Option<EntityId> {
+        let _guard = self.hierarchy.lock().unwrap();
+
+        self.detach(child)
+    }
+
+    pub fn get_parent(&self, child: EntityId) -> Option<EntityId> {
+        self.get::<Parent>(child).map(|parent| parent.0)
+    }
+
+    pub fn get_children(&self, parent: EntityId) -> Vec<EntityId> {
+        self.get::<Children>(parent).map_or_else(Vec::new, |children| children.0.clone())
+    }
+
+    // Unlinks despawned entities from parents that stay alive, the children
+    // are returned so the caller can despawn them as well.
+    pub(crate) fn unlink_despawned(&self, entity_ids: &[EntityId]) -> Vec<EntityId> {
+        let _guard = self.hierarchy.lock().unwrap();
+
+        let mut children = Vec::new();
+        for &entity_id in entity_ids {
+            if let Some(parent) = self.get_parent(entity_id) {
+                if !entity_ids.contains(&parent) {
+                    self.remove_child(parent, entity_id);
+                }
+            }
+            children.extend(self.get_children(entity_id));
+        }
+        children.retain(|child| !entity_ids.contains(child));
+
+        children
+    }
+
+    fn detach(&self, child: EntityId) -> Option<EntityId> {
+        let parent = self.remove_link::<Parent>(child)?.0;
+        self.remove_child(parent, child);
+
+        Some(parent)
+    }
+
+    fn remove_child(&self, parent: EntityId, child: EntityId) {
+        let is_empty = match self.get_mut::<Children>(parent) {
+            Some(mut children) => {
+                children.0.retain(|c| *c != child);
+                children.0.is_empty()
+            }
+            None => false,
+        };
+        if is_empty {
+            self.remove_link::<Children>(parent);
+        }
+    }
+
+    // Both link components are registered up front as sparse sets, so they
+    // can be inserted and removed without `&mut Ecs`.
+    fn insert_link<T: 'static + Component>(&self, entity_id: EntityId, link: T) {
+        let index = self.component_index::<T>().unwrap();
+        let storage = self.storage_at::<T>(index).unwrap();
+        let is_added = storage.insert(entity_id.index(), link, self.change_tick());
+
+        let hooks = &self.hooks[index];
+        if is_added {
+            run_hook(&hooks.on_add, self, entity_id);
+        }
+        run_hook(&hooks.on_insert, self, entity_id);
+    }
+
+    fn remove_link<T: 'static + Component>(&self, entity_id: EntityId) -> Option<T> {
+        let index = self.component_index::<T>().unwrap();
+        let storage = self.storage_at::<T>(index).unwrap();
+        if !storage.contains(entity_id.index()) {
+            return None;
+        }
+
+        run_hook(&self.hooks[index].on_remove, self, entity_id);
+        let link = storage.remove(entity_id.index())?;
+
+        let mut events = self.events.write().unwrap();
+        events.push_event(EcsEvent::ComponentRemoved(entity_id, TypeId::of::<T>()));
+
+        Some(link)
+    }
+
+    fn is_ancestor(&self, ancestor: EntityId, entity: EntityId) -> bool {
+        let mut current = Some(entity);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.get_parent(id);
+        }
+        false
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{Ecs, EntityId};
+
+pub type ComponentHook = Box<dyn Fn(&Ecs, EntityId) + Send + Sync>;
+
+// Callbacks fired by the world for a single component type. `on_remove` runs
+// while the component is still readable, despawn fires `on_despawn` first.
+#[derive(Default)]
+pub struct ComponentHooks {
+    pub(crate) on_add: Option<ComponentHook>,
+    pub(crate) on_insert: Option<ComponentHook>,
+    pub(crate) on_remove: Option<ComponentHook>,
+    pub(crate) on_despawn: Option<ComponentHook>,
+}
+
+impl ComponentHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_add(mut self, hook: impl Fn(&Ecs, EntityId) + Send + Sync + 'static) -> Self {
+        self.on_add = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_insert(mut self, hook: impl Fn(&Ecs, EntityId) + Send + Sync + 'static) -> Self {
+        self.on_insert = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_remove(mut self, hook: impl Fn(&Ecs, EntityId) + Send + Sync + 'static) -> Self {
+        self.on_remove = Some(Box::new(hook));
+        self
+    }
+
+    pub fn on_despawn(mut self, hook: impl Fn(&Ecs, EntityId) + Send + Sync + 'static) -> Self {
+        self.on_despawn = Some(Box::new(hook));
+        self
+    }
+}
+
+pub(crate) fn run_hook(hook: &Option<ComponentHook>, ecs: &Ecs, entity_id: EntityId) {
+    if let Some(hook) = hook {
+        hook(ecs, entity_id);
+    }
+}
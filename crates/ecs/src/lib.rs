@@ -1,510 +1,1116 @@
 use bit_vec::BitVec;
-use std::any::Any;
-use std::cell::{Cell, RefCell};
+use std::any::{Any, TypeId};
 use std::clone::Clone;
-use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fmt::Debug;
-use std::hash::{Hash, Hasher};
+use std::hash::Hash;
 use std::ops::Deref;
-use std::rc::{Rc, Weak};
-
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread::{self, ThreadId};
+
+mod access;
+mod archetype;
+mod bundle;
+mod commands;
 mod events;
-
+mod hierarchy;
+mod hooks;
+mod names;
+mod query;
+mod resources;
+mod schedule;
+mod snapshot;
+mod storage;
+mod transfer;
+
+use hooks::run_hook;
+use names::NameIndex;
+use resources::Resources;
+use transfer::ComponentTransfer;
+
+pub use access::Access;
+pub use archetype::{Archetype, Archetypes, EntityLocation};
+use archetype::{new_column, AnyColumn};
+pub use bundle::{Bundle, DynamicBundle};
+pub use commands::*;
+pub use ecs_derive::{Bundle, Component};
 pub use events::*;
+pub use hierarchy::{Children, Parent};
+pub use hooks::{ComponentHook, ComponentHooks};
+pub use names::{Name, Tags};
+pub use query::*;
+pub use schedule::*;
+pub use snapshot::*;
+pub use storage::*;
+pub use transfer::MapEntities;
 
 #[derive(Clone, Copy, Debug)]
 pub enum EcsEvent {
     EntityCreated(EntityId),
     EntityDestroyed(EntityId),
+    ComponentRemoved(EntityId, TypeId),
 }
 
-pub trait Component {
-    const INDEX: usize;
+pub trait Component: Send + Sync + 'static {}
 
-    fn get_entity_id(&self) -> Option<EntityId>;
-}
-
-trait ComponentContainer {
+trait ComponentContainer: Send + Sync {
     fn as_any(&self) -> &dyn Any;
     fn reset(&mut self, index: usize);
+    fn contains(&self, index: usize) -> bool;
 }
 
-type ComponentContainerVec<T> = Rc<RefCell<Vec<Option<T>>>>;
+type ComponentContainerVec<T> = Arc<ComponentStorage<T>>;
 
-impl<T: 'static + Component> ComponentContainer for ComponentContainerVec<T> {
-    
+struct ComponentInfo {
+    type_name: &'static str,
+    storage_type: StorageType,
+    // Only sparse set components own a container, table components live in
+    // the archetype tables.
+    sparse: Option<Box<dyn ComponentContainer>>,
+    new_column: fn() -> Box<dyn AnyColumn>,
+    transfer: ComponentTransfer,
+}
 
+impl<T: 'static + Component> ComponentContainer for ComponentContainerVec<T> {
     fn as_any(&self) -> &dyn Any {
         self
     }
 
     fn reset(&mut self, id: usize) {
-        let mut s = self.deref().borrow_mut();
-        if id < s.len() {
-            s[id] = None;
-        }
+        self.remove(id);
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        ComponentStorage::contains(self, index)
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Hash, Eq)]
-pub struct EntityId(usize);
-
-//#[derive(Debug)]
-pub struct Entity {
-    weak_ecs: EcsWeak,
-    entity_id: EntityId,
-    events: RefCell<EventSystem>,
+pub struct EntityId {
+    index: u32,
+    generation: u32,
 }
 
-impl PartialEq for Entity {
-    fn eq(&self, other: &Self) -> bool {
-        self.entity_id == other.entity_id
+impl EntityId {
+    // Never handed out by any world, so it is never alive.
+    pub const DANGLING: EntityId = EntityId {
+        index: u32::MAX,
+        generation: u32::MAX,
+    };
+
+    fn new(index: usize, generation: u32) -> Self {
+        Self {
+            index: index as u32,
+            generation,
+        }
     }
-}
 
-impl Eq for Entity {
-    fn assert_receiver_is_total_eq(&self) {}
-}
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
 
-impl Hash for Entity {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.entity_id.hash(state);
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
 }
 
-impl Entity {
-    pub fn new(ecs: &EcsRc) -> Weak<Self> {
-        let id = {
-            let ecs = ecs.deref().borrow();
-            ecs.create_entity_handle()
-        };
-        let res = Rc::new(Self {
-            weak_ecs: Rc::downgrade(ecs),
-            entity_id: id,
-            events: RefCell::new(EventSystem::new()),
-        });
+// Entities are plain ids, their components are reached through the world.
+pub type Entity = EntityId;
 
-        let ecs = ecs.deref().borrow();
+struct EntityCash {
+    generations: Vec<u32>,
+    free_indexes: Vec<usize>,
+    check_bit: BitVec,
+    // Alive entities parked by `set_enabled(false)`.
+    disabled: BitVec,
+}
 
-        ecs.add_entity(res)
+impl EntityCash {
+    const GROW_SIZE: usize = 128;
+    fn new() -> Self {
+        Self {
+            generations: Vec::new(),
+            free_indexes: Vec::with_capacity(Self::GROW_SIZE),
+            check_bit: BitVec::from_elem(Self::GROW_SIZE, false),
+            disabled: BitVec::from_elem(Self::GROW_SIZE, false),
+        }
     }
 
-    pub fn get_id(&self) -> EntityId {
-        self.entity_id
-    }
+    fn create_entity_handle(&mut self) -> EntityId {
+        if let Some(index) = self.free_indexes.pop() {
+            self.check_bit.set(index, true);
 
-    pub fn kill(&self) {
-        if let Some(rc_ecs) = self.weak_ecs.upgrade() {
-            let ecs = rc_ecs.borrow();
+            return EntityId::new(index, self.generations[index]);
+        }
 
-            ecs.remove_entity(self.entity_id);
+        let index = self.generations.len();
+        self.generations.push(0);
+        if self.check_bit.len() <= index {
+            self.check_bit.grow(Self::GROW_SIZE, false);
+            self.disabled.grow(Self::GROW_SIZE, false);
         }
-    }
+        self.check_bit.set(index, true);
 
-    pub fn add_component<T: 'static + Component>(&self, creator: impl FnOnce() -> T) {
-        if let Some(ecs) = self.weak_ecs.upgrade() {
-            let mut ecs = ecs.deref().borrow_mut();
+        EntityId::new(index, 0)
+    }
 
-            ecs.add_component(self, creator);
+    // The index is not reusable until `release_index` is called, components
+    // of the dead entity are still stored in the containers at this point.
+    fn remove_entity(&mut self, entity_id: EntityId) -> bool {
+        if !self.is_entity_alive(entity_id) {
+            return false;
         }
+
+        let index = entity_id.index();
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.check_bit.set(index, false);
+        self.disabled.set(index, false);
+
+        true
     }
 
-    pub fn visit<T: 'static + Component>(&self, f: impl FnOnce(&mut Option<T>)) {
-        if let Some(ecs) = self.weak_ecs.upgrade() {
-            let ecs = ecs.deref().borrow();
-            if ecs.is_componet_exist::<T>() {
-                ecs.visit(self.entity_id, f);
-            }
+    fn release_index(&mut self, index: usize) {
+        if !self.check_bit.get(index).unwrap_or(true) {
+            self.free_indexes.push(index);
         }
     }
 
-    pub fn get_component_clone<T: 'static + Component + Clone>(&self) -> Option<T> {
-        let ecs = self.weak_ecs.upgrade()?;
-        let ecs = ecs.deref().borrow();
+    #[inline]
+    fn is_entity_alive(&self, id: EntityId) -> bool {
+        let index = id.index();
 
-        if let Some(container) = ecs.get_container::<T>() {
-            let container = container.deref().borrow();
+        self.check_bit.get(index).unwrap_or(false)
+            && self.generations.get(index) == Some(&id.generation)
+    }
 
-            if let Some(component) = container.get(self.entity_id.0) {
-                return component.clone();
-            }
+    fn set_enabled(&mut self, entity_id: EntityId, enabled: bool) -> bool {
+        if !self.is_entity_alive(entity_id) {
+            return false;
         }
 
-        None
+        self.disabled.set(entity_id.index(), !enabled);
+        true
     }
 
-    pub fn push_event<E: Sized + Clone + 'static>(&self, event: E) {
-        let mut events = self.events.borrow_mut();
-
-        events.push_event(event);
+    #[inline]
+    fn is_index_active(&self, index: usize, include_disabled: bool) -> bool {
+        self.check_bit.get(index).unwrap_or(false)
+            && (include_disabled || !self.disabled.get(index).unwrap_or(false))
     }
 
-    pub fn process_event<E: Sized + Clone + 'static, T: 'static + Component + Listener<E>>(&self) {
-        
-        let events = self.events.borrow_mut();
-        self.visit::<T>(|component|{
-            if let Some(component) = component {
-                events.process_event(component);
-            }
-        });
+    fn get_alive_check(&self) -> BitVec {
+        self.check_bit.clone()
     }
 
-    pub fn clean_events<E: Sized + Clone + 'static>(&self) {
-        let mut events = self.events.borrow_mut();
-
-        events.clear::<E>();
-    } 
+    fn get_generations(&self) -> Vec<u32> {
+        self.generations.clone()
+    }
 }
 
-struct EntityCash {
-    entities: Vec<Option<Rc<Entity>>>,
-    free_indexes: Vec<EntityId>,
-    check_bit: BitVec,
+// Entity flags borrowed for as long as a query lives, rows are checked
+// against them without locking the entity cache again.
+pub struct EntityFlags<'w> {
+    cache: RwLockReadGuard<'w, EntityCash>,
+    include_disabled: bool,
 }
 
-impl EntityCash {
-    const GROW_SIZE: usize = 128;
-    fn new() -> Self {
-        Self {
-            entities: Vec::new(),
-            free_indexes: Vec::with_capacity(Self::GROW_SIZE),
-            check_bit: BitVec::from_elem(Self::GROW_SIZE, false),
-        }
+impl EntityFlags<'_> {
+    #[inline]
+    pub(crate) fn is_active(&self, index: usize) -> bool {
+        self.cache.is_index_active(index, self.include_disabled)
     }
 
-    fn create_entity_handle(&mut self) -> EntityId {
-        if let Some(id) = self.free_indexes.pop() {
-            self.check_bit.set(id.0, true);
-
-            return id;
-        }
+    #[inline]
+    pub(crate) fn is_alive(&self, id: EntityId) -> bool {
+        self.cache.is_entity_alive(id)
+    }
 
-        let index = self.entities.len();
-        self.entities.resize_with(index + 1, || None);
-        let iter = (index + Self::GROW_SIZE)..index;
-        self.free_indexes.extend(iter.map(EntityId));
-        if self.check_bit.len() <= index {
-            self.check_bit
-                .grow(self.check_bit.len() + Self::GROW_SIZE, false);
-        }
-        self.check_bit.set(index, true);
+    #[inline]
+    pub(crate) fn generation(&self, index: usize) -> u32 {
+        self.cache.generations[index]
+    }
 
-        EntityId(index)
+    pub(crate) fn capacity(&self) -> usize {
+        self.cache.generations.len()
     }
+}
 
-    fn add_entity(&mut self, entity: Rc<Entity>) -> Weak<Entity> {
-        self.entities[entity.get_id().0] = Some(entity.clone());
+type ListenerFn<E> = fn(&Ecs, EntityId, &E);
 
-        Rc::downgrade(&entity)
+fn notify_listener<E, T>(ecs: &Ecs, entity_id: EntityId, event: &E)
+where
+    E: 'static,
+    T: 'static + Component + Listener<E>,
+{
+    if ecs.is_componet_exist::<T>() {
+        ecs.visit::<T>(entity_id, |component| {
+            if let Some(component) = component {
+                component.on_event(event);
+            }
+        });
     }
+}
 
-    fn remove_entity(&mut self, entity_id: EntityId) {
-        self.free_indexes.push(entity_id);
-        self.entities[entity_id.0] = None;
-        self.check_bit.set(entity_id.0, false);
-    }
+static NEXT_WORLD_ID: AtomicU32 = AtomicU32::new(0);
 
-    fn is_entity_alive(&self, id: EntityId) -> bool {
-        self.check_bit.get(id.0).unwrap()
-    }
+// Tells worlds apart, entity ids and change ticks only mean something in the
+// world they came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WorldId(u32);
 
-    fn get_alive_check(&self) -> BitVec {
-        self.check_bit.clone()
+impl WorldId {
+    fn next() -> Self {
+        Self(NEXT_WORLD_ID.fetch_add(1, Ordering::Relaxed))
     }
 }
 
 pub struct Ecs {
-    entity_counter: Cell<usize>,
-    components: Vec<Option<Box<dyn ComponentContainer>>>,
-    entity_cache: RefCell<EntityCash>,
-    pub events: RefCell<EventSystem>,
+    id: WorldId,
+    entity_counter: AtomicUsize,
+    change_tick: AtomicU32,
+    // Kept per thread, so systems running in parallel each see their own.
+    last_run_ticks: Mutex<HashMap<ThreadId, u32>>,
+    // Declared access of the system running on each thread, queries and
+    // resources it borrows are checked against it in debug builds.
+    running_access: Mutex<HashMap<ThreadId, Access>>,
+    parallel: AtomicBool,
+    // Component types get their slot on first insert, so indexes only have
+    // to be unique within one world.
+    component_indexes: HashMap<TypeId, usize>,
+    components: Vec<ComponentInfo>,
+    archetypes: Archetypes,
+    hooks: Vec<ComponentHooks>,
+    entity_cache: RwLock<EntityCash>,
+    commands: Mutex<Commands>,
+    // Serializes parent changes, the links live in `Parent` and `Children`.
+    hierarchy: Mutex<()>,
+    // Pool marker type -> parked entities, see `despawn_pooled`.
+    pools: Mutex<HashMap<TypeId, Vec<EntityId>>>,
+    names: RwLock<NameIndex>,
+    resources: Resources,
+    pub events: RwLock<EventSystem>,
+    listener_readers: Mutex<HashMap<TypeId, Box<dyn Any + Send>>>,
+    // Event type -> Vec<(component type, ListenerFn<E>)>
+    listeners: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+    self_events: EventReader<EcsEvent>,
 }
 
 impl Debug for Ecs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Ecs etities count {}", self.entity_counter.get())
+        write!(f, "Ecs etities count {}", self.entity_counter.load(Ordering::Relaxed))
     }
 }
 
 impl Default for Ecs {
     fn default() -> Self {
+        Self::new()
+    }
+}
+
+// World shared between threads. Borrows wait for conflicting ones to end
+// instead of panicking like a `RefCell` would.
+pub struct EcsLock {
+    ecs: RwLock<Ecs>,
+}
+
+impl EcsLock {
+    pub fn new(ecs: Ecs) -> Self {
         Self {
-            entity_counter: Cell::new(0),
-            components: Vec::new(),
-            entity_cache: RefCell::new(EntityCash::new()),
-            events: RefCell::new(EventSystem::new()),
+            ecs: RwLock::new(ecs),
         }
     }
+
+    pub fn borrow(&self) -> RwLockReadGuard<'_, Ecs> {
+        self.ecs.read().unwrap()
+    }
+
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, Ecs> {
+        self.ecs.write().unwrap()
+    }
 }
 
-pub type EcsRc = Rc<RefCell<Ecs>>;
-pub type EcsWeak = Weak<RefCell<Ecs>>;
-pub type EntityWeak = Weak<Entity>;
+pub type EcsRc = Arc<EcsLock>;
 
 impl Ecs {
     pub fn new() -> Self {
-        let mut v = Vec::<Option<Box<dyn ComponentContainer>>>::with_capacity(128);
-        v.resize_with(128, || None);
-        Self {
-            components: v,
-            entity_counter: Cell::new(0),
-            entity_cache: RefCell::new(EntityCash::new()),
-            events: RefCell::new(EventSystem::new()),
-        }
+        let mut ecs = Self {
+            id: WorldId::next(),
+            component_indexes: HashMap::new(),
+            components: Vec::new(),
+            archetypes: Archetypes::default(),
+            hooks: Vec::new(),
+            entity_counter: AtomicUsize::new(0),
+            change_tick: AtomicU32::new(1),
+            last_run_ticks: Mutex::new(HashMap::new()),
+            running_access: Mutex::new(HashMap::new()),
+            parallel: AtomicBool::new(false),
+            entity_cache: RwLock::new(EntityCash::new()),
+            commands: Mutex::new(Commands::new()),
+            hierarchy: Mutex::new(()),
+            pools: Mutex::new(HashMap::new()),
+            names: RwLock::new(NameIndex::default()),
+            resources: Resources::new(),
+            events: RwLock::new(EventSystem::new()),
+            listener_readers: Mutex::new(HashMap::new()),
+            listeners: HashMap::new(),
+            self_events: EventReader::new(),
+        };
+        ecs.register_hierarchy();
+        ecs.register_names();
+
+        ecs
+    }
+
+    pub fn id(&self) -> WorldId {
+        self.id
     }
 
     fn create_entity_handle(&self) -> EntityId {
-        let mut cache = self.entity_cache.borrow_mut();
+        let mut cache = self.entity_cache_mut();
 
         cache.create_entity_handle()
     }
 
-    fn add_entity(&self, entity: Rc<Entity>) -> EntityWeak {
-        let mut cache = self.entity_cache.borrow_mut();
-
-        let mut events = self.events.borrow_mut();
-        events.push_event(EcsEvent::EntityCreated(entity.entity_id));
-        drop(events);
+    // Entity without components, used by spawns and deferred builders.
+    pub fn spawn_empty(&self) -> Entity {
+        let entity = self.create_entity_handle();
+        self.events.write().unwrap().push_event(EcsEvent::EntityCreated(entity));
 
-        cache.add_entity(entity)
+        entity
     }
 
     pub fn remove_entity(&self, entity_id: EntityId) {
-        let mut cache = self.entity_cache.borrow_mut();
+        self.despawn_batch([entity_id]);
+    }
+
+    // Takes the entity cache and event locks once for the whole batch.
+    pub fn despawn_batch(&self, entity_ids: impl IntoIterator<Item = EntityId>) {
+        let mut entity_ids: Vec<EntityId> = entity_ids
+            .into_iter()
+            .filter(|id| self.is_entity_alive(*id))
+            .collect();
+        entity_ids.sort_by_key(|id| id.index());
+        entity_ids.dedup();
+
+        // Hooks run while the entities and their components are still reachable.
+        for &entity_id in entity_ids.iter() {
+            for (component, hooks) in self.hooks.iter().enumerate() {
+                if self.has_component_index(component, entity_id.index()) {
+                    run_hook(&hooks.on_despawn, self, entity_id);
+                    run_hook(&hooks.on_remove, self, entity_id);
+                }
+            }
+        }
+
+        // Links are read while the entities are still alive.
+        let children = self.unlink_despawned(&entity_ids);
+
+        let mut cache = self.entity_cache_mut();
+        entity_ids.retain(|id| cache.remove_entity(*id));
+        drop(cache);
+
+        if entity_ids.is_empty() {
+            return;
+        }
 
-        let mut events = self.events.borrow_mut();
-        events.push_event(EcsEvent::EntityDestroyed(entity_id));
+        let mut events = self.events.write().unwrap();
+        for entity_id in entity_ids.iter() {
+            events.push_event(EcsEvent::EntityDestroyed(*entity_id));
+        }
         drop(events);
 
-        println!("Entity {:?} removed", entity_id);
+        self.despawn_batch(children);
+    }
+
+    // Parks the entity in the pool of `P` instead of destroying it, its index
+    // and components stay allocated for the next `spawn_pooled::<P>`.
+    pub fn despawn_pooled<P: 'static>(&self, entity_id: EntityId) -> bool {
+        let mut cache = self.entity_cache_mut();
+        if !cache.is_entity_alive(entity_id) || !cache.is_index_active(entity_id.index(), false) {
+            return false;
+        }
+        cache.set_enabled(entity_id, false);
+        drop(cache);
+
+        let mut pools = self.pools.lock().unwrap();
+        pools.entry(TypeId::of::<P>()).or_default().push(entity_id);
 
-        cache.remove_entity(entity_id);
+        true
     }
 
-    pub fn get_entity(&self, id: EntityId) -> Option<Rc<Entity>> {
-        let cache = self.entity_cache.borrow_mut();
-        if cache.is_entity_alive(id) {
-            return cache.entities[id.0].clone().into();
+    pub fn pooled_count<P: 'static>(&self) -> usize {
+        let pools = self.pools.lock().unwrap();
+
+        pools.get(&TypeId::of::<P>()).map_or(0, |pool| pool.len())
+    }
+
+    // Destroys the entities parked in the pool of `P`.
+    pub fn clear_pool<P: 'static>(&self) {
+        let pooled = self.pools.lock().unwrap().remove(&TypeId::of::<P>());
+        if let Some(pooled) = pooled {
+            self.despawn_batch(pooled);
         }
-        None
     }
 
-    pub fn process_events<E: Sized + Clone + 'static, T: 'static + Component + Listener<E>>(&self) {
-        let cache = self.entity_cache.borrow();
-        for entity in cache.entities.iter() {
-            if let Some(entity) = entity { 
-                entity.process_event::<E, T>();
-            }
+    // Enables the last parked entity of the pool of `P` that `matches`
+    // accepts, its components are kept as they were parked.
+    pub fn take_pooled<P: 'static>(&mut self, matches: impl Fn(&Ecs, Entity) -> bool) -> Option<Entity> {
+        let mut pool = self.pools.get_mut().unwrap().remove(&TypeId::of::<P>())?;
+
+        // Parked entities may have been destroyed since.
+        pool.retain(|entity_id| self.is_entity_alive(*entity_id));
+        let taken = pool
+            .iter()
+            .rposition(|entity_id| matches(self, *entity_id))
+            .map(|position| pool.remove(position));
+        self.pools.get_mut().unwrap().insert(TypeId::of::<P>(), pool);
+
+        let entity = taken?;
+        self.set_enabled(entity, true);
+        Some(entity)
+    }
+
+    fn pooled_entities(&self) -> Vec<EntityId> {
+        let pools = self.pools.lock().unwrap();
+
+        pools.values().flatten().copied().collect()
+    }
+
+    pub fn is_entity_alive(&self, id: EntityId) -> bool {
+        let cache = self.entity_cache.read().unwrap();
+
+        cache.is_entity_alive(id)
+    }
+
+    // Disabled entities stay alive but queries and `visit` skip them, filter
+    // a query with `WithDisabled` to reach them.
+    pub fn set_enabled(&self, entity_id: EntityId, enabled: bool) -> bool {
+        self.entity_cache_mut().set_enabled(entity_id, enabled)
+    }
+
+    pub fn is_entity_enabled(&self, id: EntityId) -> bool {
+        let cache = self.entity_cache.read().unwrap();
+
+        cache.is_entity_alive(id) && cache.is_index_active(id.index(), false)
+    }
+
+    pub fn send_event<E: Send + Sync + 'static>(&self, event: E) {
+        self.events.write().unwrap().push_event(event);
+    }
+
+    pub fn send_entity_event<E: Send + Sync + 'static>(&self, entity: EntityId, event: E) {
+        self.send_event(EntityEvent { entity, event });
+    }
+
+    // Swaps the event buffers, events older than two updates are dropped.
+    pub fn update_events(&self) {
+        self.events.write().unwrap().update();
+    }
+
+    // Every listener type keeps its own reader, so an event reaches each
+    // listening component once no matter how often this is called.
+    pub fn process_events<E: 'static, T: 'static + Component + Listener<E>>(&self) {
+        let mut readers = self.listener_readers.lock().unwrap();
+        let reader = readers
+            .entry(TypeId::of::<(E, T)>())
+            .or_insert_with(|| Box::new(EventReader::<EntityEvent<E>>::new()))
+            .downcast_mut::<EventReader<EntityEvent<E>>>()
+            .unwrap();
+
+        let events = self.events.read().unwrap();
+        for ev in reader.read(&events) {
+            notify_listener::<E, T>(self, ev.entity, &ev.event);
+        }
+    }
+
+    pub fn register_listener<E, T>(&mut self)
+    where
+        E: 'static,
+        T: 'static + Component + Listener<E>,
+    {
+        let listeners = self
+            .listeners
+            .entry(TypeId::of::<E>())
+            .or_insert_with(|| Box::new(Vec::<(TypeId, ListenerFn<E>)>::new()))
+            .downcast_mut::<Vec<(TypeId, ListenerFn<E>)>>()
+            .unwrap();
+
+        if !listeners.iter().any(|(id, _)| *id == TypeId::of::<T>()) {
+            listeners.push((TypeId::of::<T>(), notify_listener::<E, T>));
         }
     }
 
-    pub fn process_events_all<E: Sized + Clone + 'static>(&self) {
-        let cache = self.entity_cache.borrow();
-        for id in 0..cache.entities.len() {
-            if cache.is_entity_alive(EntityId(id)) {
-                
+    // Dispatches every new `E` to all registered listener components of the
+    // entity it was sent to.
+    pub fn process_events_all<E: 'static>(&self) {
+        let listeners = match self.listeners.get(&TypeId::of::<E>()) {
+            Some(listeners) => listeners
+                .downcast_ref::<Vec<(TypeId, ListenerFn<E>)>>()
+                .unwrap(),
+            None => return,
+        };
+
+        let mut readers = self.listener_readers.lock().unwrap();
+        let reader = readers
+            .entry(TypeId::of::<EntityEvent<E>>())
+            .or_insert_with(|| Box::new(EventReader::<EntityEvent<E>>::new()))
+            .downcast_mut::<EventReader<EntityEvent<E>>>()
+            .unwrap();
+
+        let events = self.events.read().unwrap();
+        for ev in reader.read(&events) {
+            for (_, listener) in listeners.iter() {
+                listener(self, ev.entity, &ev.event);
             }
         }
     }
 
-    pub fn clean_events<E: Sized + Clone + 'static>(&self) {
-        let cache = self.entity_cache.borrow();
-        for entity in cache.entities.iter() {
-            if let Some(entity) = entity { 
-                entity.clean_events::<E>();
+    pub fn insert_resource<R: Send + Sync + 'static>(&mut self, resource: R) {
+        self.resources.insert(resource);
+    }
+
+    pub fn remove_resource<R: Send + Sync + 'static>(&mut self) -> Option<R> {
+        self.resources.remove::<R>()
+    }
+
+    pub fn has_resource<R: Send + Sync + 'static>(&self) -> bool {
+        self.resources.contains::<R>()
+    }
+
+    pub fn resource<R: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'_, R>> {
+        self.check_access(std::any::type_name::<R>(), || Access::new().resource::<R>());
+        self.resources.get::<R>()
+    }
+
+    pub fn resource_mut<R: Send + Sync + 'static>(&self) -> Option<RwLockWriteGuard<'_, R>> {
+        self.check_access(std::any::type_name::<R>(), || Access::new().resource_mut::<R>());
+        self.resources.get_mut::<R>()
+    }
+
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick.load(Ordering::Relaxed)
+    }
+
+    pub fn increment_change_tick(&self) -> u32 {
+        self.change_tick.fetch_add(1, Ordering::Relaxed).wrapping_add(1)
+    }
+
+    // Tick of the previous run of whatever is running now on this thread,
+    // `Added` and `Changed` filters match components touched after it.
+    pub fn last_run_tick(&self) -> u32 {
+        let ticks = self.last_run_ticks.lock().unwrap();
+
+        ticks.get(&thread::current().id()).copied().unwrap_or(0)
+    }
+
+    pub fn set_last_run_tick(&self, tick: u32) {
+        let mut ticks = self.last_run_ticks.lock().unwrap();
+        ticks.insert(thread::current().id(), tick);
+    }
+
+    pub(crate) fn set_running_access(&self, access: Option<&Access>) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let mut running = self.running_access.lock().unwrap();
+        match access {
+            Some(access) => running.insert(thread::current().id(), access.clone()),
+            None => running.remove(&thread::current().id()),
+        };
+    }
+
+    // Systems run in parallel on what they declare, so borrowing anything
+    // else is a race the schedule can not see.
+    pub(crate) fn check_access(&self, name: &str, used: impl FnOnce() -> Access) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        let running = self.running_access.lock().unwrap();
+        if let Some(declared) = running.get(&thread::current().id()) {
+            assert!(
+                declared.covers(&used()),
+                "{} is not part of the access declared by the running system",
+                name
+            );
+        }
+    }
+
+    // Systems running in parallel share one queue, keep the guard short.
+    pub fn commands(&self) -> MutexGuard<'_, Commands> {
+        self.commands.lock().unwrap()
+    }
+
+    // Sync point for the deferred commands, commands queued while applying
+    // are applied in the same call.
+    pub fn apply_commands(world: &EcsRc) {
+        loop {
+            let mut commands = std::mem::take(&mut *world.deref().borrow().commands());
+            if commands.is_empty() {
+                break;
             }
+            commands.apply(world);
         }
-    } 
+    }
 
-    pub fn add_component<T>(&mut self, entity: &Entity, creator: impl FnOnce() -> T)
+    pub fn add_component<T>(&mut self, entity: Entity, creator: impl FnOnce() -> T)
     where
         T: 'static + Component,
     {
-        let entity_id = entity.entity_id.0;
-        if let Some(cont) = self.get_container::<T>() {
-            let mut c1 = cont.deref().borrow_mut();
-            if c1.len() <= entity_id {
-                c1.resize_with(entity_id + 1, || None);
-            }
-            c1[entity_id] = Some(creator());
-        } else {
-            let mut v = Vec::<Option<T>>::new();
-            v.resize_with(entity_id + 1, || None);
-            v[entity_id] = Some(creator());
-            self.components[T::INDEX] = Some(Box::new(Rc::new(RefCell::new(v))));
+        self.insert_component(entity, creator());
+    }
+
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        let entity = self.spawn_empty();
+        bundle.insert_into(self, entity);
+
+        entity
+    }
+
+    // Entities come from one lock of the entity cache and the archetype of
+    // the first bundle gets room for all of them up front.
+    pub fn spawn_batch<B: Bundle>(&mut self, bundles: impl IntoIterator<Item = B>) -> Vec<Entity> {
+        let bundles: Vec<B> = bundles.into_iter().collect();
+        let Some(first) = bundles.first() else {
+            return Vec::new();
+        };
+
+        let mut components = Vec::new();
+        first.component_indexes(self, &mut components);
+        components.retain(|&component| self.components[component].sparse.is_none());
+        self.archetypes.reserve_rows(&components, bundles.len(), |component| {
+            (self.components[component].new_column)()
+        });
+
+        let mut cache = self.entity_cache_mut();
+        let entities: Vec<Entity> = bundles.iter().map(|_| cache.create_entity_handle()).collect();
+        drop(cache);
+
+        let mut events = self.events.write().unwrap();
+        for &entity in entities.iter() {
+            events.push_event(EcsEvent::EntityCreated(entity));
+        }
+        drop(events);
+
+        for (&entity, bundle) in entities.iter().zip(bundles) {
+            self.insert_bundle(entity, bundle);
         }
+
+        entities
+    }
+
+    // Reuses an entity parked in the pool of `P` when there is one. The bundle
+    // overwrites its components, which then count as changed rather than
+    // added, and components outside the bundle are kept as they were.
+    pub fn spawn_pooled<P: 'static>(&mut self, bundle: impl Bundle) -> Entity {
+        let entity = match self.take_pooled::<P>(|_, _| true) {
+            Some(entity) => entity,
+            None => self.spawn_empty(),
+        };
+        bundle.insert_into(self, entity);
+
+        entity
     }
 
-    pub fn get_container<T: 'static + Component>(&self) -> Option<ComponentContainerVec<T>> {
-        let tid = T::INDEX;
-        if let Some(x) = &self.components[tid] {
-            if let Some(x) = x.as_any().downcast_ref::<ComponentContainerVec<T>>() {
-                return Some(x.clone());
+    // The entity moves to the archetype of the whole bundle once, hooks run
+    // after every component is in place.
+    pub fn insert_bundle(&mut self, entity_id: EntityId, bundle: impl Bundle) {
+        if !self.is_entity_alive(entity_id) {
+            return;
+        }
+
+        let mut components = Vec::new();
+        bundle.component_indexes(self, &mut components);
+        let index = entity_id.index();
+        let added: Vec<bool> = components
+            .iter()
+            .map(|&component| !self.has_component_index(component, index))
+            .collect();
+
+        let table: Vec<usize> = components
+            .iter()
+            .copied()
+            .filter(|&component| self.components[component].sparse.is_none())
+            .collect();
+        if !table.is_empty() {
+            self.archetypes.reserve(index, &table, |component| {
+                (self.components[component].new_column)()
+            });
+        }
+
+        bundle.write(self, entity_id);
+
+        for (i, &component) in components.iter().enumerate() {
+            // A type listed twice was written twice, its hooks run once.
+            if components[..i].contains(&component) {
+                continue;
             }
+            let hooks = &self.hooks[component];
+            if added[i] {
+                run_hook(&hooks.on_add, self, entity_id);
+            }
+            run_hook(&hooks.on_insert, self, entity_id);
         }
-        None
     }
 
-    pub fn get_component<T: 'static + Component + Clone>(&self, entity_id: EntityId) -> Option<T> {
-        {
-            let cache = self.entity_cache.borrow();
-            if !cache.is_entity_alive(entity_id) {
-                return None;
+    // Stores a registered component without hooks, for `Bundle::write`.
+    pub(crate) fn write_component<T: 'static + Component>(&mut self, entity_id: EntityId, component: T) {
+        if let Some(index) = self.component_index::<T>() {
+            if self.is_entity_alive(entity_id) {
+                self.insert_raw(index, entity_id.index(), component);
             }
         }
+    }
+
+    pub fn insert_component<T: 'static + Component>(&mut self, entity_id: EntityId, component: T) {
+        if !self.is_entity_alive(entity_id) {
+            return;
+        }
 
-        if let Some(container) = self.get_container::<T>() {
-            let container = container.deref().borrow();
+        let index = self.register_component::<T>();
+        let is_added = self.insert_raw(index, entity_id.index(), component);
 
-            if let Some(component) = container.get(entity_id.0) {
-                return component.clone();
-            }
+        let hooks = &self.hooks[index];
+        if is_added {
+            run_hook(&hooks.on_add, self, entity_id);
         }
-        None
+        run_hook(&hooks.on_insert, self, entity_id);
     }
 
-    pub fn is_componet_exist<T: 'static + Component>(&self) -> bool {
-        self.get_container::<T>().is_some()
-    }
-
-    pub fn visit_all<T: 'static + Component>(&self, f: impl Fn(&mut T)) {
-        if let Some(cont_1) = self.get_container::<T>() {
-            let mut c1 = cont_1.deref().borrow_mut();
-            let iter = c1.iter_mut();
-            let alive_check = {
-                let cache = self.entity_cache.borrow();
-                cache.get_alive_check()
-            };
-
-            iter.enumerate().for_each(|pair| {
-                if alive_check.get(pair.0).unwrap() {
-                    if let Some(a) = pair.1 {
-                        f(a);
-                    }
-                }
-            });
+    pub fn remove_component<T: 'static + Component>(&mut self, entity_id: EntityId) -> Option<T> {
+        if !self.is_entity_alive(entity_id) {
+            return None;
+        }
+
+        let index = self.component_index::<T>()?;
+        if !self.has_component_index(index, entity_id.index()) {
+            return None;
         }
+
+        run_hook(&self.hooks[index].on_remove, self, entity_id);
+        let component = self.remove_raw::<T>(index, entity_id.index())?;
+
+        let mut events = self.events.write().unwrap();
+        events.push_event(EcsEvent::ComponentRemoved(entity_id, TypeId::of::<T>()));
+
+        Some(component)
     }
 
-    pub fn visit_all2<A: 'static + Component, B: 'static + Component>(
-        &self,
-        f: impl Fn(&mut A, &mut B),
-    ) {
-        let cont_1 = self.get_container::<A>().unwrap();
-        let cont_2 = self.get_container::<B>().unwrap();
+    // Keeps the storage type of an earlier registration, new components go
+    // into tables.
+    pub fn register_component<T: 'static + Component>(&mut self) -> usize {
+        match self.component_index::<T>() {
+            Some(index) => index,
+            None => self.register_component_storage::<T>(StorageType::Table),
+        }
+    }
 
-        let mut c1 = cont_1.deref().borrow_mut();
-        let mut c2 = cont_2.deref().borrow_mut();
+    // The storage type is fixed by the first registration, register sparse
+    // components before anything inserts them.
+    pub fn register_component_storage<T: 'static + Component>(&mut self, storage_type: StorageType) -> usize {
+        if let Some(index) = self.component_index::<T>() {
+            let registered = self.components[index].storage_type;
+            assert!(
+                registered == storage_type,
+                "{} is already stored as {:?}, it can not change to {:?}",
+                std::any::type_name::<T>(),
+                registered,
+                storage_type
+            );
+            return index;
+        }
 
-        let len = std::cmp::min(c1.len(), c2.len());
-        let alive_check = {
-            let cache = self.entity_cache.borrow();
-            cache.get_alive_check()
-        };
-        for i in 0..len {
-            if !alive_check.get(i).unwrap() {
-                continue;
-            }
-            if let (Some(a), Some(b)) = (c1.get_mut(i), c2.get_mut(i)) {
-                if let (Some(a), Some(b)) = (a, b) {
-                    f(a, b);
-                }
+        let index = self.components.len();
+        let sparse: Option<Box<dyn ComponentContainer>> = match storage_type {
+            StorageType::Table => None,
+            StorageType::SparseSet => {
+                let storage: ComponentContainerVec<T> = Arc::new(ComponentStorage::new());
+                Some(Box::new(storage))
             }
+        };
+        self.components.push(ComponentInfo {
+            type_name: std::any::type_name::<T>(),
+            storage_type,
+            sparse,
+            new_column: new_column::<T>,
+            transfer: ComponentTransfer::new::<T>(),
+        });
+        self.hooks.push(ComponentHooks::new());
+        self.component_indexes.insert(TypeId::of::<T>(), index);
+
+        index
+    }
+
+    pub fn register_component_hooks<T: 'static + Component>(&mut self, hooks: ComponentHooks) {
+        let index = self.register_component::<T>();
+        self.hooks[index] = hooks;
+    }
+
+    pub fn component_index<T: 'static + Component>(&self) -> Option<usize> {
+        self.component_indexes.get(&TypeId::of::<T>()).copied()
+    }
+
+    pub fn storage_type<T: 'static + Component>(&self) -> Option<StorageType> {
+        Some(self.components[self.component_index::<T>()?].storage_type)
+    }
+
+    #[inline]
+    pub fn archetypes(&self) -> &Archetypes {
+        &self.archetypes
+    }
+
+    pub fn get_component<T: 'static + Component + Clone>(&self, entity: Entity) -> Option<T> {
+        let component = self.get::<T>(entity)?;
+
+        Some(component.clone())
+    }
+
+    // The column of `T` stays locked while the guard lives, drop it before
+    // writing to another `T`.
+    pub fn get<T: 'static + Component>(&self, entity: Entity) -> Option<ColumnRef<'_, T>> {
+        if !self.is_entity_alive(entity) {
+            return None;
         }
+
+        let (column, row) = self.locate::<T>(entity.index())?;
+        column.get(row)
     }
 
-    pub fn visit_all3<A, B, C>(&self, f: impl Fn(&mut A, &mut B, &mut C))
-    where
-        A: 'static + Component,
-        B: 'static + Component,
-        C: 'static + Component,
-    {
-        let cont_1 = self.get_container::<A>().unwrap();
-        let cont_2 = self.get_container::<B>().unwrap();
-        let cont_3 = self.get_container::<C>().unwrap();
-
-        let mut c1 = cont_1.deref().borrow_mut();
-        let mut c2 = cont_2.deref().borrow_mut();
-        let mut c3 = cont_3.deref().borrow_mut();
-
-        let len = std::cmp::min(std::cmp::min(c1.len(), c2.len()), c3.len());
-        let alive_check = {
-            let cache = self.entity_cache.borrow();
-            cache.get_alive_check()
-        };
-        for i in 0..len {
-            if !alive_check.get(i).unwrap() {
-                continue;
-            }
-            if let (Some(a), Some(b), Some(c)) = (c1.get_mut(i), c2.get_mut(i), c3.get_mut(i)) {
-                if let (Some(a), Some(b), Some(c)) = (a, b, c) {
-                    f(a, b, c);
-                }
-            }
+    // Writing through the guard marks the component changed. Nothing else can
+    // read a `T` while it lives.
+    pub fn get_mut<T: 'static + Component>(&self, entity: Entity) -> Option<ColumnMut<'_, T>> {
+        if !self.is_entity_alive(entity) {
+            return None;
+        }
+
+        let (column, row) = self.locate::<T>(entity.index())?;
+        column.get_mut(row, self.change_tick())
+    }
+
+    // Sparse set storage of `T`, `None` for table components.
+    fn get_storage<T: 'static + Component>(&self) -> Option<&ComponentStorage<T>> {
+        self.storage_at(self.component_index::<T>()?)
+    }
+
+    fn storage_at<T: 'static + Component>(&self, component: usize) -> Option<&ComponentStorage<T>> {
+        let container = self.components.get(component)?.sparse.as_ref()?;
+        let container = container.as_any().downcast_ref::<ComponentContainerVec<T>>()?;
+
+        Some(container.deref())
+    }
+
+    // Column and row holding the component of an entity index, for either
+    // storage type.
+    fn locate<T: 'static + Component>(&self, index: usize) -> Option<(&Column<T>, usize)> {
+        self.locate_component(self.component_index::<T>()?, index)
+    }
+
+    // Same as `locate` for callers that already looked up the component.
+    fn locate_component<T: 'static + Component>(&self, component: usize, index: usize) -> Option<(&Column<T>, usize)> {
+        if let Some(storage) = self.storage_at::<T>(component) {
+            return Some((storage.column(), storage.slot(index)?));
+        }
+
+        let location = self.archetypes.location(index)?;
+        let column = self.archetypes.get(location.archetype)?.column::<T>(component)?;
+
+        Some((column, location.row))
+    }
+
+    fn has_component_index(&self, component: usize, index: usize) -> bool {
+        match &self.components[component].sparse {
+            Some(container) => container.contains(index),
+            None => self
+                .archetypes
+                .location(index)
+                .and_then(|location| self.archetypes.get(location.archetype))
+                .is_some_and(|archetype| archetype.contains(component)),
         }
     }
 
+    // Stores the component without running hooks, returns true when the
+    // entity did not have it before.
+    fn insert_raw<T: 'static + Component>(&mut self, component: usize, index: usize, value: T) -> bool {
+        match self.storage_at::<T>(component) {
+            Some(storage) => storage.insert(index, value, self.change_tick()),
+            None => self.archetypes.insert(component, index, value, self.change_tick()),
+        }
+    }
+
+    fn remove_raw<T: 'static + Component>(&mut self, component: usize, index: usize) -> Option<T> {
+        match self.storage_at::<T>(component) {
+            Some(storage) => storage.remove(index),
+            None => self.archetypes.remove::<T>(component, index),
+        }
+    }
+
+    pub(crate) fn set_parallel(&self, parallel: bool) {
+        self.parallel.store(parallel, Ordering::Relaxed);
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        self.parallel.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn entity_flags(&self, include_disabled: bool) -> EntityFlags<'_> {
+        EntityFlags {
+            cache: self.entity_cache.read().unwrap(),
+            include_disabled,
+        }
+    }
+
+    // Queries borrow the entity flags for as long as they live, so spawning
+    // or despawning inside one panics instead of waiting forever. Systems
+    // running in parallel spawn and despawn through commands.
+    fn entity_cache_mut(&self) -> RwLockWriteGuard<'_, EntityCash> {
+        assert!(
+            !self.is_parallel(),
+            "entities can not be spawned, despawned or disabled while systems run in parallel, use commands"
+        );
+        storage::write(&self.entity_cache, "the entity list")
+    }
+
+    fn get_alive_check(&self) -> BitVec {
+        let cache = self.entity_cache.read().unwrap();
+
+        cache.get_alive_check()
+    }
+
+    fn get_generations(&self) -> Vec<u32> {
+        let cache = self.entity_cache.read().unwrap();
+
+        cache.get_generations()
+    }
+
+    pub fn is_componet_exist<T: 'static + Component>(&self) -> bool {
+        self.component_index::<T>().is_some()
+    }
+
+    pub fn query<D: QueryData>(&self) -> Query<'_, D> {
+        Query::new(self)
+    }
+
+    pub fn query_filtered<D: QueryData, F: QueryFilter>(&self) -> Query<'_, D, F> {
+        Query::new(self)
+    }
+
     pub fn visit<T>(&self, entity_id: EntityId, f: impl FnOnce(&mut Option<T>))
     where
         T: 'static + Component,
     {
-        {
-            let cache = self.entity_cache.borrow();
-            if !cache.is_entity_alive(entity_id) {
-                return;
+        if !self.is_entity_enabled(entity_id) {
+            return;
+        }
+
+        self.visit_entity(entity_id, f);
+    }
+
+    // `f` runs on a value taken out of its slot, so no column lock is held
+    // while it inserts or removes other components. A component cleared by
+    // `f` is removed, one set on an empty slot is inserted. Neither runs
+    // hooks, and table components only move at the next sync point.
+    fn visit_entity<T: 'static + Component>(&self, entity_id: EntityId, f: impl FnOnce(&mut Option<T>)) {
+        let Some(component) = self.component_index::<T>() else {
+            return;
+        };
+
+        let mut value = self.take_visited::<T>(component, entity_id.index());
+        f(&mut value);
+        self.restore_visited(component, entity_id, value);
+    }
+
+    fn take_visited<T: 'static + Component>(&self, component: usize, index: usize) -> Option<T> {
+        let (column, row) = self.locate_component::<T>(component, index)?;
+        column.take(row)
+    }
+
+    // The entity may have moved rows since its value was taken.
+    fn restore_visited<T: 'static + Component>(&self, component: usize, entity_id: EntityId, value: Option<T>) {
+        match (self.locate_component::<T>(component, entity_id.index()), value) {
+            (Some((column, row)), Some(value)) => {
+                column.restore(row, value, self.change_tick());
             }
+            (Some(_), None) => self.store_visited::<T>(component, entity_id, None),
+            (None, Some(value)) => self.store_visited(component, entity_id, Some(value)),
+            (None, None) => {}
         }
+    }
 
-        let cont_1 = self.get_container::<T>().unwrap();
-        let mut c1 = cont_1.deref().borrow_mut();
-        if let Some(elem) = c1.get_mut(entity_id.0) {
-            f(elem);
+    // Table rows only move through `&mut Ecs`, so adding or removing a table
+    // component waits in the commands.
+    fn store_visited<T: 'static + Component>(&self, component: usize, entity_id: EntityId, value: Option<T>) {
+        let index = entity_id.index();
+        if let Some(storage) = self.storage_at::<T>(component) {
+            match value {
+                Some(value) => {
+                    storage.insert(index, value, self.change_tick());
+                }
+                None => {
+                    storage.remove(index);
+                }
+            }
+            return;
         }
+
+        self.commands().add(move |world| {
+            let mut ecs = world.borrow_mut();
+            if !ecs.is_entity_alive(entity_id) {
+                return;
+            }
+            match value {
+                Some(value) => {
+                    ecs.insert_raw(component, index, value);
+                }
+                None => {
+                    ecs.remove_raw::<T>(component, index);
+                }
+            }
+        });
     }
 
-    pub fn visit2<A, B>(&self, entity: &Entity, f: impl Fn(&mut Option<A>, &mut Option<B>))
+    pub fn visit2<A, B>(&self, entity: Entity, f: impl Fn(&mut Option<A>, &mut Option<B>))
     where
         A: 'static + Component,
         B: 'static + Component,
     {
-        {
-            let cache = self.entity_cache.borrow();
-            if !cache.is_entity_alive(entity.entity_id) {
-                return;
-            }
+        if !self.is_entity_enabled(entity) {
+            return;
         }
 
-        let cont_1 = self.get_container::<A>().unwrap();
-        let cont_2 = self.get_container::<B>().unwrap();
+        let (Some(a), Some(b)) = (self.component_index::<A>(), self.component_index::<B>()) else {
+            return;
+        };
 
-        let mut c1 = cont_1.deref().borrow_mut();
-        let mut c2 = cont_2.deref().borrow_mut();
-        if let (Some(e1), Some(e2)) = (
-            c1.get_mut(entity.entity_id.0),
-            c2.get_mut(entity.entity_id.0),
-        ) {
-            f(e1, e2);
-        }
+        let index = entity.index();
+        let mut e1 = self.take_visited::<A>(a, index);
+        let mut e2 = self.take_visited::<B>(b, index);
+        f(&mut e1, &mut e2);
+        self.restore_visited(b, entity, e2);
+        self.restore_visited(a, entity, e1);
     }
 
     pub fn process_self_events(&mut self) {
-
-        let events = {
-            let events = self.events.borrow();
-            events.get_events::<EcsEvent>()
+        let destroyed: Vec<EntityId> = {
+            let events = self.events.read().unwrap();
+            self.self_events
+                .read(&events)
+                .filter_map(|ev| match ev {
+                    EcsEvent::EntityDestroyed(id) => Some(*id),
+                    _ => None,
+                })
+                .collect()
         };
-        
-        if let Some(events) = events {
-            for ev in events {
-                if let EcsEvent::EntityDestroyed(id) = ev {
-                    let mut tmp = self.components.iter_mut();
-                    while let Some(c) = tmp.next() {
-                        if let Some(c) = c {
-                            c.reset(id.0)
-                        }
-                    }
-                }
+
+        let cache = self.entity_cache.get_mut().unwrap();
+        for id in destroyed {
+            for c in self.components.iter_mut().filter_map(|c| c.sparse.as_mut()) {
+                c.reset(id.index())
             }
+            self.archetypes.remove_entity(id.index());
+            cache.release_index(id.index());
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+
+use crate::{
+    Component, ComponentHooks, Ecs, Entity, Serializable, SnapshotReader, SnapshotWriter,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Name(String);
+
+impl Component for Name {}
+
+impl Name {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serializable for Name {
+    const NAME: &'static str = "name";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_str(&self.0);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self(reader.read_str()?))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tags(Vec<String>);
+
+impl Component for Tags {}
+
+impl Tags {
+    pub fn new<S: Into<String>>(tags: impl IntoIterator<Item = S>) -> Self {
+        let mut tags: Vec<String> = tags.into_iter().map(Into::into).collect();
+        tags.sort();
+        tags.dedup();
+        Self(tags)
+    }
+
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.iter().any(|t| t == tag)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl Serializable for Tags {
+    const NAME: &'static str = "tags";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_u32(self.0.len() as u32);
+        for tag in self.0.iter() {
+            writer.write_str(tag);
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        let count = reader.read_u32()?;
+        let tags = (0..count).map(|_| reader.read_str()).collect::<Option<Vec<_>>>()?;
+
+        Some(Self::new(tags))
+    }
+}
+
+// Entities by name and by tag, kept up to date by the `Name` and `Tags`
+// hooks. Writes through queries or `get_mut` skip the hooks, insert a new
+// component to rename or retag an entity.
+#[derive(Default)]
+pub(crate) struct NameIndex {
+    names: HashMap<String, Vec<Entity>>,
+    tags: HashMap<String, Vec<Entity>>,
+    // What each entity is indexed under, so a replaced component drops its
+    // old entries.
+    entity_names: HashMap<Entity, String>,
+    entity_tags: HashMap<Entity, Vec<String>>,
+}
+
+impl NameIndex {
+    fn set_name(&mut self, entity: Entity, name: &str) {
+        self.remove_name(entity);
+        self.names.entry(name.to_string()).or_default().push(entity);
+        self.entity_names.insert(entity, name.to_string());
+    }
+
+    fn remove_name(&mut self, entity: Entity) {
+        if let Some(name) = self.entity_names.remove(&entity) {
+            remove_entry(&mut self.names, &name, entity);
+        }
+    }
+
+    fn set_tags(&mut self, entity: Entity, tags: &Tags) {
+        self.remove_tags(entity);
+        for tag in tags.iter() {
+            self.tags.entry(tag.to_string()).or_default().push(entity);
+        }
+        self.entity_tags.insert(entity, tags.0.clone());
+    }
+
+    fn remove_tags(&mut self, entity: Entity) {
+        for tag in self.entity_tags.remove(&entity).unwrap_or_default() {
+            remove_entry(&mut self.tags, &tag, entity);
+        }
+    }
+}
+
+fn remove_entry(index: &mut HashMap<String, Vec<Entity>>, key: &str, entity: Entity) {
+    if let Some(entities) = index.get_mut(key) {
+        entities.retain(|e| *e != entity);
+        if entities.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+
+fn index_name(ecs: &Ecs, entity: Entity) {
+    if let Some(name) = ecs.get::<Name>(entity) {
+        ecs.names.write().unwrap().set_name(entity, name.as_str());
+    }
+}
+
+fn unindex_name(ecs: &Ecs, entity: Entity) {
+    ecs.names.write().unwrap().remove_name(entity);
+}
+
+fn index_tags(ecs: &Ecs, entity: Entity) {
+    if let Some(tags) = ecs.get::<Tags>(entity) {
+        ecs.names.write().unwrap().set_tags(entity, &tags);
+    }
+}
+
+fn unindex_tags(ecs: &Ecs, entity: Entity) {
+    ecs.names.write().unwrap().remove_tags(entity);
+}
+
+// Drops everything up to the last path segment, generic arguments are kept.
+fn short_type_name(name: &str) -> &str {
+    let end = name.find('<').unwrap_or(name.len());
+    let start = name[..end].rfind("::").map_or(0, |i| i + 2);
+    &name[start..]
+}
+
+impl Ecs {
+    // The index relies on these hooks, replacing them stops it from updating.
+    pub(crate) fn register_names(&mut self) {
+        self.register_component_hooks::<Name>(
+            ComponentHooks::new().on_insert(index_name).on_remove(unindex_name),
+        );
+        self.register_component_hooks::<Tags>(
+            ComponentHooks::new().on_insert(index_tags).on_remove(unindex_tags),
+        );
+        self.register_clone::<Name>();
+        self.register_clone::<Tags>();
+    }
+
+    // First enabled entity with the name.
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        let index = self.names.read().unwrap();
+        let entities = index.names.get(name)?;
+
+        entities.iter().copied().find(|e| self.is_entity_enabled(*e))
+    }
+
+    // Enabled entities with the tag, in tagging order.
+    pub fn iter_tagged(&self, tag: &str) -> impl Iterator<Item = Entity> {
+        let index = self.names.read().unwrap();
+        let entities: Vec<Entity> = index
+            .tags
+            .get(tag)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|e| self.is_entity_enabled(*e))
+            .collect();
+
+        entities.into_iter()
+    }
+
+    // Name of the entity, or `index:generation` when it has none.
+    pub fn entity_label(&self, entity: Entity) -> String {
+        match self.get::<Name>(entity) {
+            Some(name) => name.to_string(),
+            None => format!("{}:{}", entity.index(), entity.generation()),
+        }
+    }
+
+    // Every alive entity with its tags, parent and component types.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+
+        let alive = self.get_alive_check();
+        let generations = self.get_generations();
+        for (index, generation) in generations.iter().enumerate() {
+            if !alive.get(index).unwrap_or(false) {
+                continue;
+            }
+
+            let entity = Entity::new(index, *generation);
+            write!(out, "{}", self.entity_label(entity)).unwrap();
+            if let Some(tags) = self.get::<Tags>(entity) {
+                write!(out, " [{}]", tags.0.join(", ")).unwrap();
+            }
+            if !self.is_entity_enabled(entity) {
+                write!(out, " disabled").unwrap();
+            }
+            writeln!(out).unwrap();
+
+            if let Some(parent) = self.get_parent(entity) {
+                writeln!(out, "  parent {}", self.entity_label(parent)).unwrap();
+            }
+
+            let components: Vec<&str> = self
+                .components
+                .iter()
+                .enumerate()
+                .filter(|(component, _)| self.has_component_index(*component, index))
+                .map(|(_, info)| short_type_name(info.type_name))
+                .collect();
+            writeln!(out, "  {}", components.join(", ")).unwrap();
+        }
+
+        out
+    }
+}
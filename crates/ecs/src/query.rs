@@ -0,0 +1,867 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::sync::{RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{
+    Access, Archetype, Column, Component, ComponentTicks, Ecs, EntityId, EntityFlags, EntityLocation, Slots,
+    TickCell,
+};
+
+pub trait QueryData {
+    type Fetch<'w>;
+    type Item<'q>;
+
+    // `None` means a required component was never added, the query is empty then.
+    fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>>;
+
+    // Components the query borrows, for scheduling systems in parallel.
+    fn access(access: &mut Access);
+
+    // Whether rows of the archetype can match, data that never rejects an
+    // entity matches every archetype.
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool;
+
+    // True when only entities with table components can match, the query
+    // then walks the matching archetypes instead of every entity.
+    fn requires_table(fetch: &Self::Fetch<'_>) -> bool;
+
+    // Entity indexes of a packed storage, visited instead of every entity.
+    fn entities<'a>(_fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        None
+    }
+
+    /// # Safety
+    /// Every index must be requested at most once per fetch and the returned
+    /// item must not outlive the fetch it was taken from.
+    unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> Option<Self::Item<'q>>;
+}
+
+pub trait QueryFilter {
+    type Fetch<'w>;
+
+    fn fetch(ecs: &Ecs) -> Self::Fetch<'_>;
+
+    fn access(access: &mut Access);
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool;
+
+    fn requires_table(_fetch: &Self::Fetch<'_>) -> bool {
+        false
+    }
+
+    // Disabled entities only match filters that opt in.
+    fn includes_disabled() -> bool {
+        false
+    }
+
+    fn matches(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> bool;
+}
+
+// Borrowed columns of one component type, one per archetype for table
+// storage or a single packed column for sparse sets.
+pub struct ColumnSet<'w, C> {
+    columns: Vec<Option<C>>,
+    slots: Option<Slots<'w>>,
+}
+
+impl<'w, C> ColumnSet<'w, C> {
+    fn new<T: 'static + Component>(ecs: &'w Ecs, borrow: impl Fn(&'w Column<T>) -> C) -> Option<Self> {
+        if let Some(storage) = ecs.get_storage::<T>() {
+            return Some(Self {
+                columns: vec![Some(borrow(storage.column()))],
+                slots: Some(storage.slots()),
+            });
+        }
+
+        let component = ecs.component_index::<T>()?;
+        let archetypes = ecs.archetypes();
+        let columns = (0..archetypes.len())
+            .map(|id| archetypes.get(id)?.column::<T>(component).map(&borrow))
+            .collect();
+
+        Some(Self {
+            columns,
+            slots: None,
+        })
+    }
+
+    #[inline]
+    fn locate(&self, index: usize, location: Option<EntityLocation>) -> Option<(&C, usize)> {
+        let (column, row) = match &self.slots {
+            Some(slots) => (0, slots.slot(index)?),
+            None => {
+                let location = location?;
+                (location.archetype, location.row)
+            }
+        };
+
+        Some((self.columns.get(column)?.as_ref()?, row))
+    }
+
+    fn matches_archetype(&self, archetype: usize) -> bool {
+        self.slots.is_some() || self.has_column(archetype)
+    }
+
+    fn has_column(&self, archetype: usize) -> bool {
+        self.slots.is_none() && self.columns.get(archetype).is_some_and(|c| c.is_some())
+    }
+
+    fn is_table(&self) -> bool {
+        self.slots.is_none()
+    }
+
+    fn owners(&self) -> Option<&[u32]> {
+        self.slots.as_ref().map(|slots| slots.owners())
+    }
+}
+
+pub struct ReadColumn<'w, T> {
+    _guard: RwLockReadGuard<'w, Vec<Option<T>>>,
+    ptr: *const Option<T>,
+    len: usize,
+}
+
+impl<'w, T> ReadColumn<'w, T> {
+    fn new(column: &'w Column<T>) -> Self {
+        let guard = column.components();
+        let ptr = guard.as_ptr();
+        let len = guard.len();
+        Self {
+            _guard: guard,
+            ptr,
+            len,
+        }
+    }
+
+    #[inline]
+    unsafe fn get<'q>(&self, row: usize) -> Option<&'q T> {
+        if row < self.len {
+            return (*self.ptr.add(row)).as_ref();
+        }
+        None
+    }
+}
+
+pub struct WriteColumn<'w, T> {
+    _guard: RwLockWriteGuard<'w, Vec<Option<T>>>,
+    ticks: RwLockReadGuard<'w, Vec<TickCell>>,
+    ptr: *mut Option<T>,
+    len: usize,
+}
+
+impl<'w, T> WriteColumn<'w, T> {
+    fn new(column: &'w Column<T>) -> Self {
+        let mut guard = column.components_mut();
+        let ptr = guard.as_mut_ptr();
+        let len = guard.len();
+        Self {
+            _guard: guard,
+            ticks: column.ticks(),
+            ptr,
+            len,
+        }
+    }
+}
+
+pub type ReadFetch<'w, T> = ColumnSet<'w, ReadColumn<'w, T>>;
+
+pub struct WriteFetch<'w, T> {
+    columns: ColumnSet<'w, WriteColumn<'w, T>>,
+    last_run: u32,
+    this_run: u32,
+}
+
+impl<'w, T> WriteFetch<'w, T> {
+    #[inline]
+    unsafe fn get<'q>(&self, index: usize, location: Option<EntityLocation>) -> Option<Mut<'q, T>> {
+        let (column, row) = self.columns.locate(index, location)?;
+        if row < column.len {
+            let value = (*column.ptr.add(row)).as_mut()?;
+            let ticks = &*(&column.ticks[row] as *const TickCell);
+            return Some(Mut {
+                value,
+                ticks,
+                last_run: self.last_run,
+                this_run: self.this_run,
+            });
+        }
+        None
+    }
+}
+
+// Mutable access to a component that bumps its change tick on write.
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    ticks: &'a TickCell,
+    last_run: u32,
+    this_run: u32,
+}
+
+impl<T> Mut<'_, T> {
+    pub fn set_changed(&mut self) {
+        if let Some(mut ticks) = self.ticks.get() {
+            ticks.set_changed(self.this_run);
+            self.ticks.set(Some(ticks));
+        }
+    }
+
+    pub fn is_added(&self) -> bool {
+        self.ticks
+            .get()
+            .is_some_and(|ticks| ticks.is_added(self.last_run, self.this_run))
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.ticks
+            .get()
+            .is_some_and(|ticks| ticks.is_changed(self.last_run, self.this_run))
+    }
+}
+
+impl<T> Deref for Mut<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for Mut<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        self.set_changed();
+        self.value
+    }
+}
+
+impl<T: Debug> Debug for Mut<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl QueryData for EntityId {
+    type Fetch<'w> = EntityFlags<'w>;
+    type Item<'q> = EntityId;
+
+    fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>> {
+        Some(ecs.entity_flags(true))
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn matches_archetype(_fetch: &Self::Fetch<'_>, _archetype: usize) -> bool {
+        true
+    }
+
+    fn requires_table(_fetch: &Self::Fetch<'_>) -> bool {
+        false
+    }
+
+    #[inline]
+    unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, _location: Option<EntityLocation>) -> Option<Self::Item<'q>> {
+        Some(EntityId::new(index, fetch.generation(index)))
+    }
+}
+
+impl<T: 'static + Component> QueryData for &T {
+    type Fetch<'w> = ReadFetch<'w, T>;
+    type Item<'q> = &'q T;
+
+    fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>> {
+        ColumnSet::new(ecs, ReadColumn::new)
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+        fetch.matches_archetype(archetype)
+    }
+
+    fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+        fetch.is_table()
+    }
+
+    fn entities<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        fetch.owners()
+    }
+
+    #[inline]
+    unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> Option<Self::Item<'q>> {
+        let (column, row) = fetch.locate(index, location)?;
+        column.get(row)
+    }
+}
+
+impl<T: 'static + Component> QueryData for &mut T {
+    type Fetch<'w> = WriteFetch<'w, T>;
+    type Item<'q> = Mut<'q, T>;
+
+    fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>> {
+        Some(WriteFetch {
+            columns: ColumnSet::new(ecs, WriteColumn::new)?,
+            last_run: ecs.last_run_tick(),
+            this_run: ecs.change_tick(),
+        })
+    }
+
+    fn access(access: &mut Access) {
+        access.add_write::<T>();
+    }
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+        fetch.columns.matches_archetype(archetype)
+    }
+
+    fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+        fetch.columns.is_table()
+    }
+
+    fn entities<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+        fetch.columns.owners()
+    }
+
+    #[inline]
+    unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> Option<Self::Item<'q>> {
+        fetch.get(index, location)
+    }
+}
+
+impl<T: 'static + Component> QueryData for Option<&T> {
+    type Fetch<'w> = Option<ReadFetch<'w, T>>;
+    type Item<'q> = Option<&'q T>;
+
+    fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>> {
+        Some(<&T>::fetch(ecs))
+    }
+
+    fn access(access: &mut Access) {
+        <&T>::access(access);
+    }
+
+    fn matches_archetype(_fetch: &Self::Fetch<'_>, _archetype: usize) -> bool {
+        true
+    }
+
+    fn requires_table(_fetch: &Self::Fetch<'_>) -> bool {
+        false
+    }
+
+    #[inline]
+    unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> Option<Self::Item<'q>> {
+        Some(fetch.as_ref().and_then(|f| <&T>::get(f, index, location)))
+    }
+}
+
+impl<T: 'static + Component> QueryData for Option<&mut T> {
+    type Fetch<'w> = Option<WriteFetch<'w, T>>;
+    type Item<'q> = Option<Mut<'q, T>>;
+
+    fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>> {
+        Some(<&mut T>::fetch(ecs))
+    }
+
+    fn access(access: &mut Access) {
+        <&mut T>::access(access);
+    }
+
+    fn matches_archetype(_fetch: &Self::Fetch<'_>, _archetype: usize) -> bool {
+        true
+    }
+
+    fn requires_table(_fetch: &Self::Fetch<'_>) -> bool {
+        false
+    }
+
+    #[inline]
+    unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> Option<Self::Item<'q>> {
+        Some(fetch.as_ref().and_then(|f| f.get(index, location)))
+    }
+}
+
+pub struct TicksFetch<'w> {
+    ticks: Option<ColumnSet<'w, RwLockReadGuard<'w, Vec<TickCell>>>>,
+    last_run: u32,
+    this_run: u32,
+}
+
+impl<'w> TicksFetch<'w> {
+    fn new<T: 'static + Component>(ecs: &'w Ecs) -> Self {
+        Self {
+            ticks: ColumnSet::new(ecs, |column: &'w Column<T>| column.ticks()),
+            last_run: ecs.last_run_tick(),
+            this_run: ecs.change_tick(),
+        }
+    }
+
+    fn get(&self, index: usize, location: Option<EntityLocation>) -> Option<ComponentTicks> {
+        let (ticks, row) = self.ticks.as_ref()?.locate(index, location)?;
+        ticks.get(row)?.get()
+    }
+
+    fn matches_archetype(&self, archetype: usize) -> bool {
+        self.ticks
+            .as_ref()
+            .is_some_and(|ticks| ticks.matches_archetype(archetype))
+    }
+
+    // An unregistered component is on no entity, so no archetype matches.
+    fn requires_table(&self) -> bool {
+        self.ticks.as_ref().is_none_or(|ticks| ticks.is_table())
+    }
+}
+
+pub struct With<T>(PhantomData<T>);
+
+pub struct Without<T>(PhantomData<T>);
+
+pub struct Added<T>(PhantomData<T>);
+
+pub struct Changed<T>(PhantomData<T>);
+
+// Lets disabled entities through, queries skip them otherwise.
+pub struct WithDisabled;
+
+impl<T: 'static + Component> QueryFilter for With<T> {
+    type Fetch<'w> = TicksFetch<'w>;
+
+    fn fetch(ecs: &Ecs) -> Self::Fetch<'_> {
+        TicksFetch::new::<T>(ecs)
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+        fetch.matches_archetype(archetype)
+    }
+
+    fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+        fetch.requires_table()
+    }
+
+    #[inline]
+    fn matches(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> bool {
+        fetch.get(index, location).is_some()
+    }
+}
+
+impl<T: 'static + Component> QueryFilter for Without<T> {
+    type Fetch<'w> = TicksFetch<'w>;
+
+    fn fetch(ecs: &Ecs) -> Self::Fetch<'_> {
+        TicksFetch::new::<T>(ecs)
+    }
+
+    fn access(_access: &mut Access) {}
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+        !fetch
+            .ticks
+            .as_ref()
+            .is_some_and(|ticks| ticks.has_column(archetype))
+    }
+
+    #[inline]
+    fn matches(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> bool {
+        fetch.get(index, location).is_none()
+    }
+}
+
+impl<T: 'static + Component> QueryFilter for Added<T> {
+    type Fetch<'w> = TicksFetch<'w>;
+
+    fn fetch(ecs: &Ecs) -> Self::Fetch<'_> {
+        TicksFetch::new::<T>(ecs)
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+        fetch.matches_archetype(archetype)
+    }
+
+    fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+        fetch.requires_table()
+    }
+
+    #[inline]
+    fn matches(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> bool {
+        fetch
+            .get(index, location)
+            .is_some_and(|ticks| ticks.is_added(fetch.last_run, fetch.this_run))
+    }
+}
+
+impl<T: 'static + Component> QueryFilter for Changed<T> {
+    type Fetch<'w> = TicksFetch<'w>;
+
+    fn fetch(ecs: &Ecs) -> Self::Fetch<'_> {
+        TicksFetch::new::<T>(ecs)
+    }
+
+    fn access(access: &mut Access) {
+        access.add_read::<T>();
+    }
+
+    fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+        fetch.matches_archetype(archetype)
+    }
+
+    fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+        fetch.requires_table()
+    }
+
+    #[inline]
+    fn matches(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> bool {
+        fetch
+            .get(index, location)
+            .is_some_and(|ticks| ticks.is_changed(fetch.last_run, fetch.this_run))
+    }
+}
+
+impl QueryFilter for WithDisabled {
+    type Fetch<'w> = ();
+
+    fn fetch(_ecs: &Ecs) -> Self::Fetch<'_> {}
+
+    fn access(_access: &mut Access) {}
+
+    fn matches_archetype(_fetch: &Self::Fetch<'_>, _archetype: usize) -> bool {
+        true
+    }
+
+    fn includes_disabled() -> bool {
+        true
+    }
+
+    fn matches(_fetch: &Self::Fetch<'_>, _index: usize, _location: Option<EntityLocation>) -> bool {
+        true
+    }
+}
+
+impl QueryFilter for () {
+    type Fetch<'w> = ();
+
+    fn fetch(_ecs: &Ecs) -> Self::Fetch<'_> {}
+
+    fn access(_access: &mut Access) {}
+
+    fn matches_archetype(_fetch: &Self::Fetch<'_>, _archetype: usize) -> bool {
+        true
+    }
+
+    fn matches(_fetch: &Self::Fetch<'_>, _index: usize, _location: Option<EntityLocation>) -> bool {
+        true
+    }
+}
+
+macro_rules! impl_query_tuple {
+    ($($name: ident),*) => {
+        #[allow(non_snake_case)]
+        impl<$($name: QueryData),*> QueryData for ($($name,)*) {
+            type Fetch<'w> = ($($name::Fetch<'w>,)*);
+            type Item<'q> = ($($name::Item<'q>,)*);
+
+            fn fetch(ecs: &Ecs) -> Option<Self::Fetch<'_>> {
+                Some(($($name::fetch(ecs)?,)*))
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+                let ($($name,)*) = fetch;
+                $($name::matches_archetype($name, archetype))&&*
+            }
+
+            fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+                let ($($name,)*) = fetch;
+                $($name::requires_table($name))||*
+            }
+
+            // The shortest packed set bounds the whole query.
+            fn entities<'a>(fetch: &'a Self::Fetch<'_>) -> Option<&'a [u32]> {
+                let ($($name,)*) = fetch;
+                let mut entities: Option<&'a [u32]> = None;
+                $(
+                    if let Some(e) = $name::entities($name) {
+                        if entities.is_none_or(|entities| e.len() < entities.len()) {
+                            entities = Some(e);
+                        }
+                    }
+                )*
+                entities
+            }
+
+            #[inline]
+            unsafe fn get<'q>(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> Option<Self::Item<'q>> {
+                let ($($name,)*) = fetch;
+                Some(($($name::get($name, index, location)?,)*))
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($name: QueryFilter),*> QueryFilter for ($($name,)*) {
+            type Fetch<'w> = ($($name::Fetch<'w>,)*);
+
+            fn fetch(ecs: &Ecs) -> Self::Fetch<'_> {
+                ($($name::fetch(ecs),)*)
+            }
+
+            fn access(access: &mut Access) {
+                $($name::access(access);)*
+            }
+
+            fn matches_archetype(fetch: &Self::Fetch<'_>, archetype: usize) -> bool {
+                let ($($name,)*) = fetch;
+                $($name::matches_archetype($name, archetype))&&*
+            }
+
+            fn requires_table(fetch: &Self::Fetch<'_>) -> bool {
+                let ($($name,)*) = fetch;
+                $($name::requires_table($name))||*
+            }
+
+            fn includes_disabled() -> bool {
+                $($name::includes_disabled())||*
+            }
+
+            #[inline]
+            fn matches(fetch: &Self::Fetch<'_>, index: usize, location: Option<EntityLocation>) -> bool {
+                let ($($name,)*) = fetch;
+                $($name::matches($name, index, location))&&*
+            }
+        }
+    };
+}
+
+impl_query_tuple!(A);
+impl_query_tuple!(A, B);
+impl_query_tuple!(A, B, C);
+impl_query_tuple!(A, B, C, D);
+impl_query_tuple!(A, B, C, D, E);
+impl_query_tuple!(A, B, C, D, E, F);
+impl_query_tuple!(A, B, C, D, E, F, G);
+impl_query_tuple!(A, B, C, D, E, F, G, H);
+impl_query_tuple!(A, B, C, D, E, F, G, H, I);
+impl_query_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_query_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_query_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+pub struct Query<'w, D: QueryData, F: QueryFilter = ()> {
+    ecs: &'w Ecs,
+    entities: EntityFlags<'w>,
+    data: Option<D::Fetch<'w>>,
+    filter: F::Fetch<'w>,
+}
+
+impl<'w, D: QueryData, F: QueryFilter> Query<'w, D, F> {
+    pub fn new(ecs: &'w Ecs) -> Self {
+        // Release builds still panic on the conflicting column borrow, this
+        // only names the query.
+        if cfg!(debug_assertions) {
+            let mut access = Access::new();
+            D::access(&mut access);
+            assert!(
+                access.aliased().is_empty(),
+                "{} borrows {} more than once while writing it",
+                std::any::type_name::<D>(),
+                access.aliased().join(", ")
+            );
+        }
+        ecs.check_access(std::any::type_name::<Self>(), || Access::new().query_filtered::<D, F>());
+
+        Self {
+            ecs,
+            entities: ecs.entity_flags(F::includes_disabled()),
+            data: D::fetch(ecs),
+            filter: F::fetch(ecs),
+        }
+    }
+
+    #[inline]
+    fn matches(&self, index: usize, location: Option<EntityLocation>) -> bool {
+        self.entities.is_active(index) && F::matches(&self.filter, index, location)
+    }
+
+    // Table queries walk the rows of the matching archetypes, a packed set
+    // smaller than those is walked instead.
+    fn plan(&self) -> QueryPlan<'w, '_> {
+        let Some(data) = &self.data else {
+            return QueryPlan::Scan(0);
+        };
+
+        let entities = D::entities(data);
+        if !D::requires_table(data) && !F::requires_table(&self.filter) {
+            return match entities {
+                Some(entities) => QueryPlan::Entities(entities),
+                None => QueryPlan::Scan(self.entities.capacity()),
+            };
+        }
+
+        let archetypes = self.ecs.archetypes();
+        let matched: Vec<&'w Archetype> = (0..archetypes.len())
+            .filter_map(|id| archetypes.get(id))
+            .filter(|archetype| {
+                D::matches_archetype(data, archetype.id())
+                    && F::matches_archetype(&self.filter, archetype.id())
+            })
+            .collect();
+
+        let rows: usize = matched.iter().map(|archetype| archetype.len()).sum();
+        match entities {
+            Some(entities) if entities.len() < rows => QueryPlan::Entities(entities),
+            _ => QueryPlan::Archetypes(matched),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> QueryIter<'_, 'w, D, F> {
+        QueryIter {
+            plan: self.plan(),
+            query: self,
+            rows: None,
+            cursor: 0,
+            row: 0,
+        }
+    }
+
+    pub fn iter_with_id(&mut self) -> QueryIdIter<'_, 'w, D, F> {
+        QueryIdIter {
+            iter: self.iter_mut(),
+        }
+    }
+
+    // Items borrow the query mutably, so only one can be alive at a time.
+    pub fn get(&mut self, entity_id: EntityId) -> Option<D::Item<'_>> {
+        if !self.entities.is_alive(entity_id) {
+            return None;
+        }
+
+        let index = entity_id.index();
+        let location = self.ecs.archetypes().location(index);
+        if !self.matches(index, location) {
+            return None;
+        }
+
+        let data = self.data.as_ref()?;
+        unsafe { D::get(data, index, location) }
+    }
+
+    // The only matching item, `None` when nothing or more than one entity matches.
+    pub fn single(&mut self) -> Option<D::Item<'_>> {
+        let mut iter = self.iter_mut();
+        let item = iter.next()?;
+        if iter.next().is_some() {
+            return None;
+        }
+        Some(item)
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        self.iter_mut().next().is_none()
+    }
+}
+
+enum QueryPlan<'w, 'q> {
+    Archetypes(Vec<&'w Archetype>),
+    Entities(&'q [u32]),
+    Scan(usize),
+}
+
+pub struct QueryIter<'q, 'w, D: QueryData, F: QueryFilter> {
+    query: &'q Query<'w, D, F>,
+    plan: QueryPlan<'w, 'q>,
+    // Entities of the archetype being walked.
+    rows: Option<(usize, RwLockReadGuard<'w, Vec<u32>>)>,
+    cursor: usize,
+    row: usize,
+}
+
+impl<'q, D: QueryData, F: QueryFilter> QueryIter<'q, '_, D, F> {
+    #[inline]
+    fn next_location(&mut self) -> Option<(usize, Option<EntityLocation>)> {
+        let archetypes = self.query.ecs.archetypes();
+        match &self.plan {
+            QueryPlan::Archetypes(matched) => loop {
+                if let Some((archetype, entities)) = &self.rows {
+                    let row = self.row;
+                    if let Some(&index) = entities.get(row) {
+                        self.row += 1;
+                        let location = EntityLocation {
+                            archetype: *archetype,
+                            row,
+                        };
+                        return Some((index as usize, Some(location)));
+                    }
+                }
+
+                let archetype = matched.get(self.cursor)?;
+                self.rows = Some((archetype.id(), archetype.entities()));
+                self.cursor += 1;
+                self.row = 0;
+            },
+            QueryPlan::Entities(entities) => {
+                let index = *entities.get(self.cursor)? as usize;
+                self.cursor += 1;
+                Some((index, archetypes.location(index)))
+            }
+            QueryPlan::Scan(len) => {
+                if self.cursor >= *len {
+                    return None;
+                }
+                let index = self.cursor;
+                self.cursor += 1;
+                Some((index, archetypes.location(index)))
+            }
+        }
+    }
+
+    #[inline]
+    fn next_indexed(&mut self) -> Option<(usize, D::Item<'q>)> {
+        let data = self.query.data.as_ref()?;
+        while let Some((index, location)) = self.next_location() {
+            if !self.query.matches(index, location) {
+                continue;
+            }
+
+            // Each index is visited once and the iterator holds the query
+            // exclusively, so items never alias.
+            if let Some(item) = unsafe { D::get(data, index, location) } {
+                return Some((index, item));
+            }
+        }
+        None
+    }
+}
+
+impl<'q, D: QueryData, F: QueryFilter> Iterator for QueryIter<'q, '_, D, F> {
+    type Item = D::Item<'q>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_indexed().map(|(_, item)| item)
+    }
+}
+
+pub struct QueryIdIter<'q, 'w, D: QueryData, F: QueryFilter> {
+    iter: QueryIter<'q, 'w, D, F>,
+}
+
+impl<'q, D: QueryData, F: QueryFilter> Iterator for QueryIdIter<'q, '_, D, F> {
+    type Item = (EntityId, D::Item<'q>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (index, item) = self.iter.next_indexed()?;
+        let generation = self.iter.query.entities.generation(index);
+
+        Some((EntityId::new(index, generation), item))
+    }
+}
@@ -0,0 +1,46 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[derive(Default)]
+pub struct Resources {
+    storage: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Resources {
+    pub fn new() -> Self {
+        Self {
+            storage: HashMap::new(),
+        }
+    }
+
+    pub fn insert<R: Send + Sync + 'static>(&mut self, resource: R) {
+        self.storage
+            .insert(TypeId::of::<R>(), Box::new(RwLock::new(resource)));
+    }
+
+    pub fn remove<R: Send + Sync + 'static>(&mut self) -> Option<R> {
+        let resource = self.storage.remove(&TypeId::of::<R>())?;
+        let resource = resource.downcast::<RwLock<R>>().ok()?;
+
+        resource.into_inner().ok()
+    }
+
+    pub fn contains<R: Send + Sync + 'static>(&self) -> bool {
+        self.storage.contains_key(&TypeId::of::<R>())
+    }
+
+    pub fn get<R: Send + Sync + 'static>(&self) -> Option<RwLockReadGuard<'_, R>> {
+        Some(self.get_lock::<R>()?.read().unwrap())
+    }
+
+    pub fn get_mut<R: Send + Sync + 'static>(&self) -> Option<RwLockWriteGuard<'_, R>> {
+        Some(self.get_lock::<R>()?.write().unwrap())
+    }
+
+    fn get_lock<R: Send + Sync + 'static>(&self) -> Option<&RwLock<R>> {
+        let resource = self.storage.get(&TypeId::of::<R>())?;
+
+        resource.downcast_ref::<RwLock<R>>()
+    }
+}
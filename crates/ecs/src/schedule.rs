@@ -0,0 +1,377 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+
+use crate::{Access, Ecs, EcsRc, WorldId};
+
+pub trait System: Send {
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    // Systems that declare their access run in parallel with the ones they
+    // do not conflict with. They only borrow the world immutably and leave
+    // structural changes to commands. `None` runs the system alone.
+    fn access(&self) -> Option<Access> {
+        None
+    }
+
+    fn update(&mut self, world: &EcsRc);
+}
+
+impl<F: FnMut(&EcsRc) + Send> System for F {
+    fn update(&mut self, world: &EcsRc) {
+        self(world)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Stage {
+    Input,
+    Simulation,
+    Collision,
+    Cleanup,
+    Render,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Input,
+        Stage::Simulation,
+        Stage::Collision,
+        Stage::Cleanup,
+        Stage::Render,
+    ];
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScheduleError {
+    DuplicateLabel(String),
+    UnknownLabel { system: String, label: String },
+    Cycle(Vec<String>),
+}
+
+impl Display for ScheduleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleError::DuplicateLabel(label) => write!(f, "more than one system is labeled {}", label),
+            ScheduleError::UnknownLabel { system, label } => {
+                write!(f, "system {} is ordered against unknown label {}", system, label)
+            }
+            ScheduleError::Cycle(labels) => {
+                write!(f, "systems have cyclic ordering: {}", labels.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+pub type RunCondition = Box<dyn Fn(&Ecs) -> bool + Send + Sync>;
+
+pub struct SystemConfig {
+    system: Box<dyn System>,
+    label: Option<String>,
+    before: Vec<String>,
+    after: Vec<String>,
+    conditions: Vec<RunCondition>,
+    access: Option<Access>,
+}
+
+impl SystemConfig {
+    pub fn new(system: impl System + 'static) -> Self {
+        Self {
+            system: Box::new(system),
+            label: None,
+            before: Vec::new(),
+            after: Vec::new(),
+            conditions: Vec::new(),
+            access: None,
+        }
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    pub fn before(mut self, label: &str) -> Self {
+        self.before.push(label.to_string());
+        self
+    }
+
+    pub fn after(mut self, label: &str) -> Self {
+        self.after.push(label.to_string());
+        self
+    }
+
+    pub fn run_if(mut self, condition: impl Fn(&Ecs) -> bool + Send + Sync + 'static) -> Self {
+        self.conditions.push(Box::new(condition));
+        self
+    }
+
+    // Overrides the access declared by the system, mostly for closures.
+    pub fn access(mut self, access: Access) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    fn get_label(&self) -> String {
+        self.label
+            .clone()
+            .unwrap_or_else(|| self.system.name().to_string())
+    }
+}
+
+impl<S: System + 'static> From<S> for SystemConfig {
+    fn from(system: S) -> Self {
+        SystemConfig::new(system)
+    }
+}
+
+struct SystemNode {
+    label: String,
+    stage: Stage,
+    access: Option<Access>,
+    config: SystemConfig,
+    last_run: u32,
+}
+
+impl SystemNode {
+    fn should_run(&self, world: &Ecs) -> bool {
+        self.config.conditions.iter().all(|condition| condition(world))
+    }
+
+    fn is_ordered_against(&self, other: &SystemNode) -> bool {
+        self.config.before.contains(&other.label)
+            || self.config.after.contains(&other.label)
+            || other.config.before.contains(&self.label)
+            || other.config.after.contains(&self.label)
+    }
+
+    fn run(&mut self, world: &EcsRc) {
+        let this_run = {
+            let ecs = world.deref().borrow();
+            ecs.set_last_run_tick(self.last_run);
+            ecs.set_running_access(self.access.as_ref());
+            ecs.increment_change_tick()
+        };
+        self.config.system.update(world);
+        world.deref().borrow().set_running_access(None);
+        self.last_run = this_run;
+        // Writes made after the run, by later systems, commands or outside
+        // the schedule, must be newer than its last run.
+        world.deref().borrow().increment_change_tick();
+    }
+}
+
+#[derive(Default)]
+pub struct Schedule {
+    systems: Vec<SystemNode>,
+    // Systems in sorted order, grouped into batches that run in parallel.
+    batches: Option<Vec<Vec<usize>>>,
+    // The last run ticks of the systems belong to this world, they start over
+    // when the schedule runs on another one, e.g. a loaded snapshot.
+    world: Option<WorldId>,
+}
+
+impl Schedule {
+    pub fn new() -> Self {
+        Self {
+            systems: Vec::new(),
+            batches: None,
+            world: None,
+        }
+    }
+
+    pub fn add_system(&mut self, stage: Stage, config: impl Into<SystemConfig>) -> &mut Self {
+        let config = config.into();
+        self.systems.push(SystemNode {
+            label: config.get_label(),
+            stage,
+            access: config.access.clone().or_else(|| config.system.access()),
+            config,
+            last_run: 0,
+        });
+        self.batches = None;
+        self
+    }
+
+    pub fn build(&mut self) -> Result<(), ScheduleError> {
+        // Ordering refers to systems by label, so every label has to name one.
+        for (i, node) in self.systems.iter().enumerate() {
+            if self.systems[..i].iter().any(|other| other.label == node.label) {
+                return Err(ScheduleError::DuplicateLabel(node.label.clone()));
+            }
+        }
+
+        let mut batches = Vec::new();
+        for stage in Stage::ALL {
+            let sorted = self.sort_stage(stage)?;
+            batches.extend(self.batch_stage(&sorted));
+        }
+        self.batches = Some(batches);
+
+        Ok(())
+    }
+
+    // Labels of the systems in each batch, in run order.
+    pub fn batches(&self) -> Vec<Vec<&str>> {
+        self.batches
+            .iter()
+            .flatten()
+            .map(|batch| batch.iter().map(|&i| self.systems[i].label.as_str()).collect())
+            .collect()
+    }
+
+    pub fn run(&mut self, world: &EcsRc) {
+        let world_id = world.deref().borrow().id();
+        if self.world != Some(world_id) {
+            for node in self.systems.iter_mut() {
+                node.last_run = 0;
+            }
+            self.world = Some(world_id);
+        }
+
+        if self.batches.is_none() {
+            if let Err(err) = self.build() {
+                panic!("Failed to build schedule: {}", err);
+            }
+        }
+
+        let batches = self.batches.take().unwrap_or_default();
+        let mut current_stage = None;
+        for batch in batches.iter() {
+            let stage = self.systems[batch[0]].stage;
+            if current_stage.is_some_and(|current| current != stage) {
+                Ecs::apply_commands(world);
+            }
+            current_stage = Some(stage);
+
+            let mut nodes: Vec<&mut SystemNode> = self
+                .systems
+                .iter_mut()
+                .enumerate()
+                .filter(|(index, _)| batch.contains(index))
+                .map(|(_, node)| node)
+                .filter(|node| node.should_run(&world.deref().borrow()))
+                .collect();
+
+            match nodes.len() {
+                0 => {}
+                1 => nodes[0].run(world),
+                _ => {
+                    world.deref().borrow().set_parallel(true);
+                    rayon::scope(|scope| {
+                        for node in nodes.iter_mut() {
+                            scope.spawn(move |_| node.run(world));
+                        }
+                    });
+                    world.deref().borrow().set_parallel(false);
+                }
+            }
+        }
+        self.batches = Some(batches);
+
+        Ecs::apply_commands(world);
+        world.deref().borrow_mut().process_self_events();
+        world.deref().borrow().update_events();
+    }
+
+    // Sorted systems join the last batch while they neither conflict with
+    // nor are ordered against any of its members.
+    fn batch_stage(&self, sorted: &[usize]) -> Vec<Vec<usize>> {
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        for &i in sorted {
+            let fits = batches
+                .last()
+                .is_some_and(|batch| batch.iter().all(|&j| self.can_run_together(i, j)));
+            match batches.last_mut() {
+                Some(batch) if fits => batch.push(i),
+                _ => batches.push(vec![i]),
+            }
+        }
+
+        batches
+    }
+
+    fn can_run_together(&self, a: usize, b: usize) -> bool {
+        let (a, b) = (&self.systems[a], &self.systems[b]);
+        match (&a.access, &b.access) {
+            (Some(access_a), Some(access_b)) => !access_a.conflicts_with(access_b) && !a.is_ordered_against(b),
+            _ => false,
+        }
+    }
+
+    fn find_label(&self, label: &str) -> Option<usize> {
+        self.systems.iter().position(|node| node.label == label)
+    }
+
+    // Kahn's algorithm over the systems of one stage, ordering constraints to
+    // systems of other stages only have to agree with the stage order.
+    fn sort_stage(&self, stage: Stage) -> Result<Vec<usize>, ScheduleError> {
+        let nodes: Vec<usize> = (0..self.systems.len())
+            .filter(|&i| self.systems[i].stage == stage)
+            .collect();
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+
+        for &i in nodes.iter() {
+            let node = &self.systems[i];
+            let constraints = node
+                .config
+                .before
+                .iter()
+                .map(|label| (label, true))
+                .chain(node.config.after.iter().map(|label| (label, false)));
+
+            for (label, is_before) in constraints {
+                let other = self
+                    .find_label(label)
+                    .ok_or_else(|| ScheduleError::UnknownLabel {
+                        system: node.label.clone(),
+                        label: label.clone(),
+                    })?;
+                let other_stage = self.systems[other].stage;
+
+                if other_stage == stage {
+                    edges.push(if is_before { (i, other) } else { (other, i) });
+                } else if (other_stage < stage) == is_before {
+                    return Err(ScheduleError::Cycle(vec![
+                        node.label.clone(),
+                        label.clone(),
+                    ]));
+                }
+            }
+        }
+
+        let mut in_degree: Vec<usize> = vec![0; self.systems.len()];
+        for &(_, to) in edges.iter() {
+            in_degree[to] += 1;
+        }
+
+        let mut ready: VecDeque<usize> = nodes.iter().copied().filter(|&i| in_degree[i] == 0).collect();
+        let mut sorted = Vec::with_capacity(nodes.len());
+        while let Some(i) = ready.pop_front() {
+            sorted.push(i);
+            for &(from, to) in edges.iter() {
+                if from == i {
+                    in_degree[to] -= 1;
+                    if in_degree[to] == 0 {
+                        ready.push_back(to);
+                    }
+                }
+            }
+        }
+
+        if sorted.len() != nodes.len() {
+            let cycle = nodes
+                .iter()
+                .filter(|&&i| in_degree[i] > 0)
+                .map(|&i| self.systems[i].label.clone())
+                .collect();
+            return Err(ScheduleError::Cycle(cycle));
+        }
+
+        Ok(sorted)
+    }
+}
@@ -0,0 +1,397 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter, Write};
+use std::ops::Deref;
+
+use crate::{Component, Ecs, EcsRc, EntityId};
+
+pub const SNAPSHOT_VERSION: u32 = 2;
+const SNAPSHOT_HEADER: &str = "ecs-snapshot";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    MissingHeader,
+    UnsupportedVersion(u32),
+    UnknownComponent { line: usize, name: String },
+    InvalidLine { line: usize, message: String },
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::MissingHeader => write!(f, "snapshot header is missing"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "snapshot version {} is not supported", version)
+            }
+            SnapshotError::UnknownComponent { line, name } => {
+                write!(f, "line {}: unknown component {}", line, name)
+            }
+            SnapshotError::InvalidLine { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+// Old entity ids to the entities they became in a restored snapshot or in
+// the world they were moved to.
+#[derive(Default, Debug)]
+pub struct EntityMap {
+    map: HashMap<EntityId, EntityId>,
+}
+
+impl EntityMap {
+    pub(crate) fn insert(&mut self, old: EntityId, new: EntityId) {
+        self.map.insert(old, new);
+    }
+
+    pub fn get(&self, old: EntityId) -> Option<EntityId> {
+        self.map.get(&old).copied()
+    }
+
+    // References to entities that were not saved become dangling, their old
+    // id could belong to another entity in the restored world.
+    pub fn remap(&self, old: EntityId) -> EntityId {
+        self.get(old).unwrap_or(EntityId::DANGLING)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+pub struct SnapshotWriter {
+    tokens: Vec<String>,
+}
+
+impl SnapshotWriter {
+    fn new() -> Self {
+        Self { tokens: Vec::new() }
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.tokens.push(value.to_string());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.tokens.push(value.to_string());
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.tokens.push(value.to_string());
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.tokens.push(if value { "1" } else { "0" }.to_string());
+    }
+
+    // Every string starts with a quote so empty strings still make a token.
+    pub fn write_str(&mut self, value: &str) {
+        let mut token = String::from("'");
+        for c in value.chars() {
+            match c {
+                '\\' => token.push_str("\\\\"),
+                ' ' => token.push_str("\\s"),
+                '\t' => token.push_str("\\t"),
+                '\n' => token.push_str("\\n"),
+                '\r' => token.push_str("\\r"),
+                c => token.push(c),
+            }
+        }
+        self.tokens.push(token);
+    }
+
+    pub fn write_entity(&mut self, value: EntityId) {
+        self.tokens
+            .push(format!("{}:{}", value.index(), value.generation()));
+    }
+
+    fn finish(self) -> String {
+        self.tokens.join(" ")
+    }
+}
+
+pub struct SnapshotReader<'a> {
+    tokens: std::str::SplitWhitespace<'a>,
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn new(data: &'a str) -> Self {
+        Self {
+            tokens: data.split_whitespace(),
+        }
+    }
+
+    pub fn read_u32(&mut self) -> Option<u32> {
+        self.tokens.next()?.parse().ok()
+    }
+
+    pub fn read_i32(&mut self) -> Option<i32> {
+        self.tokens.next()?.parse().ok()
+    }
+
+    pub fn read_f32(&mut self) -> Option<f32> {
+        self.tokens.next()?.parse().ok()
+    }
+
+    pub fn read_bool(&mut self) -> Option<bool> {
+        match self.tokens.next()? {
+            "1" => Some(true),
+            "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn read_str(&mut self) -> Option<String> {
+        let token = self.tokens.next()?.strip_prefix('\'')?;
+        let mut value = String::with_capacity(token.len());
+        let mut chars = token.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            match chars.next()? {
+                '\\' => value.push('\\'),
+                's' => value.push(' '),
+                't' => value.push('\t'),
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                _ => return None,
+            }
+        }
+        Some(value)
+    }
+
+    pub fn read_entity(&mut self) -> Option<EntityId> {
+        parse_entity(self.tokens.next()?)
+    }
+}
+
+fn parse_entity(token: &str) -> Option<EntityId> {
+    let (index, generation) = token.split_once(':')?;
+
+    Some(EntityId::new(index.parse().ok()?, generation.parse().ok()?))
+}
+
+pub trait Serializable: Component + Sized {
+    // Stable name written to the snapshot, must not change between versions.
+    const NAME: &'static str;
+
+    fn save(&self, writer: &mut SnapshotWriter);
+
+    // Entity ids read here are remapped afterwards when the type is
+    // registered with `Ecs::register_map_entities`.
+    fn load(reader: &mut SnapshotReader) -> Option<Self>;
+}
+
+type SaveFn = fn(&Ecs, EntityId) -> Option<String>;
+type LoadFn = fn(&mut Ecs, EntityId, &str, &EntityMap) -> bool;
+
+struct SnapshotEntry {
+    name: &'static str,
+    save: SaveFn,
+    load: LoadFn,
+}
+
+fn save_component<T: Serializable>(ecs: &Ecs, entity_id: EntityId) -> Option<String> {
+    let component = ecs.get::<T>(entity_id)?;
+
+    let mut writer = SnapshotWriter::new();
+    component.save(&mut writer);
+
+    Some(writer.finish())
+}
+
+fn load_component<T: Serializable>(ecs: &mut Ecs, entity_id: EntityId, data: &str, map: &EntityMap) -> bool {
+    let mut reader = SnapshotReader::new(data);
+    match T::load(&mut reader) {
+        Some(mut component) => {
+            ecs.map_entities_of(&mut component, map);
+            ecs.insert_component(entity_id, component);
+            true
+        }
+        None => false,
+    }
+}
+
+struct EntityRecord<'a> {
+    id: EntityId,
+    parent: Option<EntityId>,
+    disabled: bool,
+    components: Vec<(usize, &'a str, &'a str)>,
+}
+
+// Text format, one record per line:
+//   ecs-snapshot <version>
+//   entity <index>:<generation>
+//   parent <index>:<generation>
+//   disabled
+//   component <name> <tokens..>
+#[derive(Default)]
+pub struct SnapshotRegistry {
+    entries: Vec<SnapshotEntry>,
+}
+
+impl SnapshotRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T: Serializable>(mut self) -> Self {
+        if !self.entries.iter().any(|entry| entry.name == T::NAME) {
+            self.entries.push(SnapshotEntry {
+                name: T::NAME,
+                save: save_component::<T>,
+                load: load_component::<T>,
+            });
+        }
+        self
+    }
+
+    pub fn save(&self, ecs: &Ecs) -> String {
+        let mut out = String::new();
+        writeln!(out, "{} {}", SNAPSHOT_HEADER, SNAPSHOT_VERSION).unwrap();
+
+        let alive = ecs.get_alive_check();
+        let generations = ecs.get_generations();
+        // Parked pool entities are spare storage, not world state.
+        let pooled: HashSet<EntityId> = ecs.pooled_entities().into_iter().collect();
+        for (index, generation) in generations.iter().enumerate() {
+            if !alive.get(index).unwrap_or(false) {
+                continue;
+            }
+
+            let entity_id = EntityId::new(index, *generation);
+            if pooled.contains(&entity_id) {
+                continue;
+            }
+            writeln!(out, "entity {}:{}", index, generation).unwrap();
+            if let Some(parent) = ecs.get_parent(entity_id) {
+                writeln!(out, "parent {}:{}", parent.index(), parent.generation()).unwrap();
+            }
+            if !ecs.is_entity_enabled(entity_id) {
+                writeln!(out, "disabled").unwrap();
+            }
+            for entry in self.entries.iter() {
+                if let Some(data) = (entry.save)(ecs, entity_id) {
+                    writeln!(out, "component {} {}", entry.name, data).unwrap();
+                }
+            }
+        }
+
+        out
+    }
+
+    pub fn load(&self, world: &EcsRc, data: &str) -> Result<EntityMap, SnapshotError> {
+        let records = self.parse(data)?;
+
+        let mut ecs = world.deref().borrow_mut();
+
+        let mut map = EntityMap::default();
+        for record in records.iter() {
+            map.insert(record.id, ecs.spawn_empty());
+        }
+
+        for record in records.iter() {
+            let entity_id = map.remap(record.id);
+            if let Some(parent) = record.parent {
+                ecs.set_parent(entity_id, map.remap(parent));
+            }
+            if record.disabled {
+                ecs.set_enabled(entity_id, false);
+            }
+
+            for &(line, name, data) in record.components.iter() {
+                let entry = self.find_entry(name).unwrap();
+                if !(entry.load)(&mut ecs, entity_id, data, &map) {
+                    // A broken snapshot leaves nothing behind.
+                    ecs.despawn_batch(map.map.values().copied());
+                    return Err(SnapshotError::InvalidLine {
+                        line,
+                        message: format!("failed to load component {}", name),
+                    });
+                }
+            }
+        }
+
+        Ok(map)
+    }
+
+    fn find_entry(&self, name: &str) -> Option<&SnapshotEntry> {
+        self.entries.iter().find(|entry| entry.name == name)
+    }
+
+    fn parse<'a>(&self, data: &'a str) -> Result<Vec<EntityRecord<'a>>, SnapshotError> {
+        let mut lines = data.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        let version = match lines.next() {
+            Some((_, header)) => header
+                .strip_prefix(SNAPSHOT_HEADER)
+                .and_then(|version| version.trim().parse::<u32>().ok())
+                .ok_or(SnapshotError::MissingHeader)?,
+            None => return Err(SnapshotError::MissingHeader),
+        };
+        // Version 1 had no disabled records, it still loads as is.
+        if version == 0 || version > SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let invalid = |line: usize, message: &str| SnapshotError::InvalidLine {
+            line,
+            message: message.to_string(),
+        };
+
+        let mut records: Vec<EntityRecord> = Vec::new();
+        for (line, text) in lines {
+            if text.is_empty() {
+                continue;
+            }
+
+            let (kind, rest) = text.split_once(' ').unwrap_or((text, ""));
+            match kind {
+                "entity" => {
+                    let id = parse_entity(rest.trim()).ok_or_else(|| invalid(line, "invalid entity id"))?;
+                    records.push(EntityRecord {
+                        id,
+                        parent: None,
+                        disabled: false,
+                        components: Vec::new(),
+                    });
+                }
+                "parent" => {
+                    let parent = parse_entity(rest.trim()).ok_or_else(|| invalid(line, "invalid parent id"))?;
+                    let record = records
+                        .last_mut()
+                        .ok_or_else(|| invalid(line, "parent outside of entity"))?;
+                    record.parent = Some(parent);
+                }
+                "disabled" => {
+                    let record = records
+                        .last_mut()
+                        .ok_or_else(|| invalid(line, "disabled outside of entity"))?;
+                    record.disabled = true;
+                }
+                "component" => {
+                    let (name, data) = rest.split_once(' ').unwrap_or((rest, ""));
+                    if self.find_entry(name).is_none() {
+                        return Err(SnapshotError::UnknownComponent {
+                            line,
+                            name: name.to_string(),
+                        });
+                    }
+                    let record = records
+                        .last_mut()
+                        .ok_or_else(|| invalid(line, "component outside of entity"))?;
+                    record.components.push((line, name, data));
+                }
+                _ => return Err(invalid(line, "unknown record")),
+            }
+        }
+
+        Ok(records)
+    }
+}
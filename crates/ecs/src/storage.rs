@@ -0,0 +1,384 @@
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, TryLockResult};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComponentTicks {
+    added: u32,
+    changed: u32,
+}
+
+impl ComponentTicks {
+    fn new(tick: u32) -> Self {
+        Self {
+            added: tick,
+            changed: tick,
+        }
+    }
+
+    pub fn is_added(&self, last_run: u32, this_run: u32) -> bool {
+        is_newer_tick(self.added, last_run, this_run)
+    }
+
+    pub fn is_changed(&self, last_run: u32, this_run: u32) -> bool {
+        is_newer_tick(self.changed, last_run, this_run)
+    }
+
+    pub fn set_changed(&mut self, tick: u32) {
+        self.changed = tick;
+    }
+}
+
+// Ticks wrap around, so the age relative to the current tick is compared
+// instead of the raw values.
+fn is_newer_tick(tick: u32, last_run: u32, this_run: u32) -> bool {
+    this_run.wrapping_sub(tick) < this_run.wrapping_sub(last_run)
+}
+
+// Ticks of one row, atomic so they can be bumped through a shared borrow of
+// the tick list.
+#[derive(Debug, Default)]
+pub struct TickCell {
+    present: AtomicBool,
+    added: AtomicU32,
+    changed: AtomicU32,
+}
+
+impl TickCell {
+    pub fn new(ticks: Option<ComponentTicks>) -> Self {
+        let cell = Self::default();
+        cell.set(ticks);
+        cell
+    }
+
+    #[inline]
+    pub fn get(&self) -> Option<ComponentTicks> {
+        if !self.present.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        Some(ComponentTicks {
+            added: self.added.load(Ordering::Relaxed),
+            changed: self.changed.load(Ordering::Relaxed),
+        })
+    }
+
+    #[inline]
+    pub fn set(&self, ticks: Option<ComponentTicks>) {
+        if let Some(ticks) = ticks {
+            self.added.store(ticks.added, Ordering::Relaxed);
+            self.changed.store(ticks.changed, Ordering::Relaxed);
+        }
+        self.present.store(ticks.is_some(), Ordering::Relaxed);
+    }
+}
+
+// Borrows never wait. Conflicting ones come from the same thread, e.g. a
+// write inside a query over the same component, and would wait forever.
+// Systems running in parallel never share a component one of them writes.
+fn try_borrow<G>(result: TryLockResult<G>, name: &str, conflict: &str) -> G {
+    match result {
+        Ok(guard) => guard,
+        Err(TryLockError::WouldBlock) => panic!("{} is already borrowed{}", name, conflict),
+        Err(TryLockError::Poisoned(err)) => panic!("{} is poisoned: {}", name, err),
+    }
+}
+
+pub(crate) fn read<'a, V>(lock: &'a RwLock<V>, name: &str) -> RwLockReadGuard<'a, V> {
+    try_borrow(lock.try_read(), name, " mutably")
+}
+
+pub(crate) fn write<'a, V>(lock: &'a RwLock<V>, name: &str) -> RwLockWriteGuard<'a, V> {
+    try_borrow(lock.try_write(), name, "")
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StorageType {
+    // Stored in the table of the entity archetype, fastest to iterate.
+    #[default]
+    Table,
+    // Packed slots plus an index map, adding and removing never moves the
+    // other components of the entity.
+    SparseSet,
+}
+
+// Ticks live apart from the components so filters can check them while the
+// components are borrowed mutably by the same query.
+pub struct Column<T> {
+    components: RwLock<Vec<Option<T>>>,
+    ticks: RwLock<Vec<TickCell>>,
+}
+
+impl<T> Column<T> {
+    pub fn new() -> Self {
+        Self {
+            components: RwLock::new(Vec::new()),
+            ticks: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn components(&self) -> RwLockReadGuard<'_, Vec<Option<T>>> {
+        read(&self.components, std::any::type_name::<T>())
+    }
+
+    pub fn components_mut(&self) -> RwLockWriteGuard<'_, Vec<Option<T>>> {
+        write(&self.components, std::any::type_name::<T>())
+    }
+
+    pub fn ticks(&self) -> RwLockReadGuard<'_, Vec<TickCell>> {
+        read(&self.ticks, std::any::type_name::<T>())
+    }
+
+    fn ticks_mut(&self) -> RwLockWriteGuard<'_, Vec<TickCell>> {
+        write(&self.ticks, std::any::type_name::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.components().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, row: usize) -> Option<ColumnRef<'_, T>> {
+        let components = self.components();
+        components.get(row)?.as_ref()?;
+
+        Some(ColumnRef { components, row })
+    }
+
+    pub fn get_mut(&self, row: usize, tick: u32) -> Option<ColumnMut<'_, T>> {
+        let components = self.components_mut();
+        components.get(row)?.as_ref()?;
+
+        Some(ColumnMut {
+            components,
+            ticks: self.ticks(),
+            row,
+            tick,
+        })
+    }
+
+    pub(crate) fn push(&self, component: T, tick: u32) {
+        self.components_mut().push(Some(component));
+        self.ticks_mut()
+            .push(TickCell::new(Some(ComponentTicks::new(tick))));
+    }
+
+    // Row filled later by `replace`, it reads as missing until then.
+    pub(crate) fn push_empty(&self) {
+        self.components_mut().push(None);
+        self.ticks_mut().push(TickCell::new(None));
+    }
+
+    pub(crate) fn reserve(&self, additional: usize) {
+        self.components_mut().reserve(additional);
+        self.ticks_mut().reserve(additional);
+    }
+
+    pub(crate) fn replace(&self, row: usize, component: T, tick: u32) {
+        let mut components = self.components_mut();
+        let ticks = self.ticks();
+
+        let row_ticks = match ticks[row].get() {
+            Some(mut row_ticks) if components[row].is_some() => {
+                row_ticks.set_changed(tick);
+                row_ticks
+            }
+            _ => ComponentTicks::new(tick),
+        };
+        components[row] = Some(component);
+        ticks[row].set(Some(row_ticks));
+    }
+
+    pub(crate) fn take(&self, row: usize) -> Option<T> {
+        self.components_mut().get_mut(row)?.take()
+    }
+
+    pub(crate) fn swap_remove(&self, row: usize) -> Option<T> {
+        self.ticks_mut().swap_remove(row);
+        self.components_mut().swap_remove(row)
+    }
+
+    // Moves the row to the end of `dst`, the last row fills the hole.
+    pub(crate) fn move_row(&self, row: usize, dst: &Column<T>) {
+        let ticks = self.ticks_mut().swap_remove(row);
+        let component = self.components_mut().swap_remove(row);
+        dst.ticks_mut().push(ticks);
+        dst.components_mut().push(component);
+    }
+
+    // Puts back a value taken out by `take`, its ticks were left in place.
+    pub(crate) fn restore(&self, row: usize, component: T, tick: u32) {
+        let mut components = self.components_mut();
+        let ticks = self.ticks();
+
+        let row_ticks = match ticks[row].get() {
+            Some(mut row_ticks) => {
+                row_ticks.set_changed(tick);
+                row_ticks
+            }
+            None => ComponentTicks::new(tick),
+        };
+        components[row] = Some(component);
+        ticks[row].set(Some(row_ticks));
+    }
+}
+
+impl<T> Default for Column<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Shared borrow of a single component in a column.
+pub struct ColumnRef<'a, T> {
+    components: RwLockReadGuard<'a, Vec<Option<T>>>,
+    row: usize,
+}
+
+impl<T> Deref for ColumnRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.components[self.row].as_ref().unwrap()
+    }
+}
+
+// Exclusive borrow of a single component, writing through it bumps the
+// change tick like `Mut` does.
+pub struct ColumnMut<'a, T> {
+    components: RwLockWriteGuard<'a, Vec<Option<T>>>,
+    ticks: RwLockReadGuard<'a, Vec<TickCell>>,
+    row: usize,
+    tick: u32,
+}
+
+impl<T> Deref for ColumnMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.components[self.row].as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for ColumnMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        let cell = &self.ticks[self.row];
+        if let Some(mut ticks) = cell.get() {
+            ticks.set_changed(self.tick);
+            cell.set(Some(ticks));
+        }
+        self.components[self.row].as_mut().unwrap()
+    }
+}
+
+#[derive(Default)]
+pub struct SparseIndex {
+    slots: Vec<Option<u32>>,
+    owners: Vec<u32>,
+}
+
+impl SparseIndex {
+    #[inline]
+    fn slot(&self, index: usize) -> Option<usize> {
+        self.slots.get(index).copied().flatten().map(|slot| slot as usize)
+    }
+}
+
+// Entity index to slot lookup, borrowed for as long as the slots are.
+pub struct Slots<'w> {
+    sparse: RwLockReadGuard<'w, SparseIndex>,
+}
+
+impl Slots<'_> {
+    #[inline]
+    pub fn slot(&self, index: usize) -> Option<usize> {
+        self.sparse.slot(index)
+    }
+
+    // Entity indexes that own a slot, in slot order.
+    pub fn owners(&self) -> &[u32] {
+        &self.sparse.owners
+    }
+}
+
+// Sparse set storage of one component type, table components live in the
+// archetype tables instead.
+pub struct ComponentStorage<T> {
+    column: Column<T>,
+    sparse: RwLock<SparseIndex>,
+}
+
+impl<T> ComponentStorage<T> {
+    pub fn new() -> Self {
+        Self {
+            column: Column::new(),
+            sparse: RwLock::new(SparseIndex::default()),
+        }
+    }
+
+    pub fn column(&self) -> &Column<T> {
+        &self.column
+    }
+
+    pub fn slots(&self) -> Slots<'_> {
+        Slots {
+            sparse: read(&self.sparse, std::any::type_name::<T>()),
+        }
+    }
+
+    pub fn slot(&self, index: usize) -> Option<usize> {
+        self.slots().slot(index)
+    }
+
+    // Only the slot is checked, so this works while a query borrows the column.
+    pub fn contains(&self, index: usize) -> bool {
+        self.slot(index).is_some()
+    }
+
+    pub fn get(&self, index: usize) -> Option<ColumnRef<'_, T>> {
+        self.column.get(self.slot(index)?)
+    }
+
+    // Returns true when the component was not there before.
+    pub fn insert(&self, index: usize, component: T, tick: u32) -> bool {
+        if let Some(slot) = self.slot(index) {
+            self.column.replace(slot, component, tick);
+            return false;
+        }
+
+        let mut sparse = write(&self.sparse, std::any::type_name::<T>());
+        let slot = sparse.owners.len();
+        if sparse.slots.len() <= index {
+            sparse.slots.resize(index + 1, None);
+        }
+        sparse.slots[index] = Some(slot as u32);
+        sparse.owners.push(index as u32);
+        drop(sparse);
+
+        self.column.push(component, tick);
+        true
+    }
+
+    // Swaps the last slot into the hole to keep the set packed.
+    pub fn remove(&self, index: usize) -> Option<T> {
+        let mut sparse = write(&self.sparse, std::any::type_name::<T>());
+        let slot = sparse.slot(index)?;
+        sparse.slots[index] = None;
+        sparse.owners.swap_remove(slot);
+        if let Some(&moved) = sparse.owners.get(slot) {
+            sparse.slots[moved as usize] = Some(slot as u32);
+        }
+        drop(sparse);
+
+        self.column.swap_remove(slot)
+    }
+}
+
+impl<T> Default for ComponentStorage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
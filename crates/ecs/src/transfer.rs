@@ -0,0 +1,159 @@
+use std::any::Any;
+
+use crate::{Children, Component, Ecs, Entity, EntityMap, Parent};
+
+// Components holding entity ids, their ids are remapped when they are moved
+// or cloned into another world and when a snapshot is loaded. Worlds only
+// remap types registered with `register_map_entities`.
+pub trait MapEntities {
+    fn map_entities(&mut self, map: &EntityMap);
+}
+
+type AnyComponent = Box<dyn Any + Send>;
+
+// Type erased ways to take a component out of one world and insert it into
+// another. Moving works for every component, cloning and remapping only for
+// types registered with `register_clone` and `register_map_entities`.
+pub(crate) struct ComponentTransfer {
+    take: fn(&mut Ecs, Entity) -> Option<AnyComponent>,
+    clone: Option<fn(&Ecs, Entity) -> Option<AnyComponent>>,
+    map_entities: Option<fn(&mut dyn Any, &EntityMap)>,
+    insert: fn(&mut Ecs, Entity, AnyComponent),
+}
+
+impl ComponentTransfer {
+    pub(crate) fn new<T: Component>() -> Self {
+        Self {
+            take: take_component::<T>,
+            clone: None,
+            map_entities: None,
+            insert: insert_component::<T>,
+        }
+    }
+}
+
+fn take_component<T: Component>(ecs: &mut Ecs, entity: Entity) -> Option<AnyComponent> {
+    Some(Box::new(ecs.remove_component::<T>(entity)?))
+}
+
+fn clone_component<T: Component + Clone>(ecs: &Ecs, entity: Entity) -> Option<AnyComponent> {
+    Some(Box::new(ecs.get::<T>(entity)?.clone()))
+}
+
+fn map_component<T: Component + MapEntities>(component: &mut dyn Any, map: &EntityMap) {
+    if let Some(component) = component.downcast_mut::<T>() {
+        component.map_entities(map);
+    }
+}
+
+fn insert_component<T: Component>(ecs: &mut Ecs, entity: Entity, component: AnyComponent) {
+    if let Ok(component) = component.downcast::<T>() {
+        ecs.insert_component(entity, *component);
+    }
+}
+
+impl Ecs {
+    pub fn register_clone<T: Component + Clone>(&mut self) {
+        let index = self.register_component::<T>();
+        self.components[index].transfer.clone = Some(clone_component::<T>);
+    }
+
+    pub fn register_map_entities<T: Component + MapEntities>(&mut self) {
+        let index = self.register_component::<T>();
+        self.components[index].transfer.map_entities = Some(map_component::<T>);
+    }
+
+    pub(crate) fn map_entities_of<T: Component>(&self, component: &mut T, map: &EntityMap) {
+        let Some(index) = self.component_index::<T>() else {
+            return;
+        };
+        if let Some(map_entities) = self.components[index].transfer.map_entities {
+            map_entities(component, map);
+        }
+    }
+
+    // Moves the entity and its children into `dst`. Components leave through
+    // the remove hooks of this world and arrive through the hooks of `dst`.
+    // The map holds the new ids, references to entities that did not move
+    // become dangling.
+    pub fn move_entity(&mut self, entity: Entity, dst: &mut Ecs) -> Option<EntityMap> {
+        let (map, parts) = self.prepare_transfer(entity, dst)?;
+        for (old, component) in parts {
+            let take = self.components[component].transfer.take;
+            if let Some(value) = take(self, old) {
+                self.insert_transferred(component, value, map.remap(old), dst, &map);
+            }
+        }
+        self.remove_entity(entity);
+
+        Some(map)
+    }
+
+    // Like `move_entity` but leaves the entity in place. Components not
+    // registered with `register_clone` are left out.
+    pub fn clone_entity(&self, entity: Entity, dst: &mut Ecs) -> Option<EntityMap> {
+        let (map, parts) = self.prepare_transfer(entity, dst)?;
+        for (old, component) in parts {
+            let Some(clone) = self.components[component].transfer.clone else {
+                continue;
+            };
+            if let Some(value) = clone(self, old) {
+                self.insert_transferred(component, value, map.remap(old), dst, &map);
+            }
+        }
+
+        Some(map)
+    }
+
+    // Spawns the new entities in `dst` and links them, then lists the
+    // components each old entity hands over.
+    fn prepare_transfer(&self, entity: Entity, dst: &mut Ecs) -> Option<(EntityMap, Vec<(Entity, usize)>)> {
+        if !self.is_entity_alive(entity) {
+            return None;
+        }
+
+        let mut entities = vec![entity];
+        let mut next = 0;
+        while let Some(&parent) = entities.get(next) {
+            entities.extend(self.get_children(parent));
+            next += 1;
+        }
+
+        // Links are rebuilt with `set_parent` in `dst`.
+        let links = [self.component_index::<Parent>(), self.component_index::<Children>()];
+
+        let mut map = EntityMap::default();
+        for &old in entities.iter() {
+            map.insert(old, dst.spawn_empty());
+        }
+
+        let mut parts = Vec::new();
+        for &old in entities.iter() {
+            let new = map.remap(old);
+            if old != entity {
+                if let Some(parent) = self.get_parent(old) {
+                    dst.set_parent(new, map.remap(parent));
+                }
+            }
+            if !self.is_entity_enabled(old) {
+                dst.set_enabled(new, false);
+            }
+
+            for component in 0..self.components.len() {
+                if self.has_component_index(component, old.index()) && !links.contains(&Some(component)) {
+                    parts.push((old, component));
+                }
+            }
+        }
+
+        Some((map, parts))
+    }
+
+    fn insert_transferred(&self, component: usize, mut value: AnyComponent, new: Entity, dst: &mut Ecs, map: &EntityMap) {
+        let transfer = &self.components[component].transfer;
+        if let Some(map_entities) = transfer.map_entities {
+            map_entities(value.as_mut(), map);
+        }
+        (transfer.insert)(dst, new, value);
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Vel(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Frozen;
+
+#[test]
+fn entities_with_the_same_components_share_an_archetype() {
+    let mut ecs = Ecs::new();
+    let a = ecs.spawn((Pos(0), Vel(0)));
+    let b = ecs.spawn((Vel(1), Pos(1)));
+    let c = ecs.spawn(Pos(2));
+
+    let location = |id: EntityId| ecs.archetypes().location(id.index()).unwrap();
+    assert_eq!(location(a).archetype, location(b).archetype);
+    assert_ne!(location(a).archetype, location(c).archetype);
+    assert_eq!(ecs.archetypes().get(location(a).archetype).unwrap().len(), 2);
+}
+
+#[test]
+fn moving_between_tables_keeps_the_other_components() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn((Pos(1), Vel(2)));
+    let before = ecs.archetypes().location(entity.index()).unwrap();
+
+    ecs.insert_component(entity, Frozen);
+    let after = ecs.archetypes().location(entity.index()).unwrap();
+    assert_ne!(before.archetype, after.archetype);
+    assert_eq!(*ecs.get::<Pos>(entity).unwrap(), Pos(1));
+    assert_eq!(*ecs.get::<Vel>(entity).unwrap(), Vel(2));
+
+    ecs.remove_component::<Frozen>(entity);
+    assert_eq!(ecs.archetypes().location(entity.index()).unwrap().archetype, before.archetype);
+    assert_eq!(*ecs.get::<Vel>(entity).unwrap(), Vel(2));
+}
+
+#[test]
+fn leaving_a_table_keeps_the_rows_left_behind() {
+    let mut ecs = Ecs::new();
+    let ids: Vec<EntityId> = (0..4).map(|i| ecs.spawn((Pos(i), Vel(i * 10)))).collect();
+
+    ecs.insert_component(ids[0], Frozen);
+    ecs.remove_entity(ids[1]);
+
+    for &id in &ids[2..] {
+        let pos = ecs.get::<Pos>(id).unwrap().0;
+        assert_eq!(ecs.get::<Vel>(id).unwrap().0, pos * 10);
+    }
+    assert_eq!(ecs.query_filtered::<&Pos, Without<Frozen>>().iter_mut().count(), 2);
+}
+
+#[test]
+fn table_changes_made_during_a_query_wait_for_the_commands() {
+    let mut ecs = Ecs::new();
+    ecs.register_component::<Frozen>();
+    let entity = ecs.spawn(Pos(1));
+    let world: EcsRc = Arc::new(EcsLock::new(ecs));
+
+    {
+        let ecs = world.borrow();
+        for (id, _) in ecs.query::<&Pos>().iter_with_id() {
+            ecs.visit::<Frozen>(id, |frozen| *frozen = Some(Frozen));
+        }
+        assert!(ecs.get::<Frozen>(entity).is_none());
+    }
+
+    Ecs::apply_commands(&world);
+
+    assert!(world.borrow().get::<Frozen>(entity).is_some());
+}
@@ -0,0 +1,90 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Health(u32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Label<T: Send + Sync + 'static>(T);
+
+#[derive(Bundle)]
+struct Named {
+    pos: Pos,
+    health: Health,
+}
+
+#[derive(Bundle)]
+struct Tuple(Pos, Health);
+
+#[derive(Bundle)]
+struct Generic<T: Send + Sync + 'static> {
+    label: Label<T>,
+    inner: Named,
+}
+
+#[test]
+fn named_fields_become_components() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Named {
+        pos: Pos(1.0, 2.0),
+        health: Health(3),
+    });
+
+    assert_eq!(*ecs.get::<Pos>(entity).unwrap(), Pos(1.0, 2.0));
+    assert_eq!(*ecs.get::<Health>(entity).unwrap(), Health(3));
+}
+
+#[test]
+fn tuple_fields_become_components() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Tuple(Pos(4.0, 5.0), Health(6)));
+
+    assert_eq!(*ecs.get::<Pos>(entity).unwrap(), Pos(4.0, 5.0));
+    assert_eq!(*ecs.get::<Health>(entity).unwrap(), Health(6));
+}
+
+#[test]
+fn generic_bundles_nest_other_bundles() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Generic {
+        label: Label("tank"),
+        inner: Named {
+            pos: Pos(0.0, 0.0),
+            health: Health(1),
+        },
+    });
+    let other = ecs.spawn(Generic {
+        label: Label(7_u32),
+        inner: Named {
+            pos: Pos(1.0, 1.0),
+            health: Health(2),
+        },
+    });
+
+    assert_eq!(ecs.get::<Label<&str>>(entity).unwrap().0, "tank");
+    assert!(ecs.get::<Label<u32>>(entity).is_none());
+    assert_eq!(ecs.get::<Label<u32>>(other).unwrap().0, 7);
+    assert_eq!(ecs.query::<(&Pos, &Health)>().iter_mut().count(), 2);
+}
+
+static SEEN_HEALTH: AtomicUsize = AtomicUsize::new(0);
+
+#[test]
+fn hooks_run_after_the_whole_bundle_is_written() {
+    let mut ecs = Ecs::new();
+    ecs.register_component_hooks::<Pos>(ComponentHooks::new().on_add(|ecs, entity| {
+        if ecs.get::<Health>(entity).is_some() {
+            SEEN_HEALTH.fetch_add(1, Ordering::Relaxed);
+        }
+    }));
+
+    ecs.spawn(Named {
+        pos: Pos(0.0, 0.0),
+        health: Health(1),
+    });
+    assert_eq!(SEEN_HEALTH.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ecs::*;
+
+#[derive(Component)]
+struct Pos(f32);
+
+static ADDED: AtomicUsize = AtomicUsize::new(0);
+static CHANGED: AtomicUsize = AtomicUsize::new(0);
+
+fn count_changes(world: &EcsRc) {
+    let ecs = world.borrow();
+    ADDED.store(ecs.query_filtered::<EntityId, Added<Pos>>().iter_mut().count(), Ordering::Relaxed);
+    CHANGED.store(ecs.query_filtered::<EntityId, Changed<Pos>>().iter_mut().count(), Ordering::Relaxed);
+}
+
+#[test]
+fn writes_between_runs_are_seen_by_the_next_run() {
+    let mut ecs = Ecs::new();
+    let first = ecs.spawn(Pos(0.0));
+    let second = ecs.spawn(Pos(0.0));
+    let world: EcsRc = Arc::new(EcsLock::new(ecs));
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Simulation, count_changes);
+    schedule.run(&world);
+    assert_eq!(ADDED.load(Ordering::Relaxed), 2);
+
+    schedule.run(&world);
+    assert_eq!(ADDED.load(Ordering::Relaxed), 0);
+    assert_eq!(CHANGED.load(Ordering::Relaxed), 0);
+
+    world.borrow().get_mut::<Pos>(first).unwrap().0 = 1.0;
+    world.borrow_mut().spawn(Pos(0.0));
+    world.borrow().commands().insert(second, Pos(2.0));
+    Ecs::apply_commands(&world);
+
+    // Replacing a component changes it, only the spawn adds one.
+    schedule.run(&world);
+    assert_eq!(ADDED.load(Ordering::Relaxed), 1);
+    assert_eq!(CHANGED.load(Ordering::Relaxed), 3);
+}
@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Hp(u32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Dead;
+
+fn world() -> EcsRc {
+    let mut ecs = Ecs::new();
+    ecs.spawn(Hp(0));
+    ecs.spawn(Hp(5));
+    Arc::new(EcsLock::new(ecs))
+}
+
+#[test]
+fn commands_wait_for_the_sync_point() {
+    let world = world();
+    {
+        let ecs = world.borrow();
+        for (id, hp) in ecs.query::<&Hp>().iter_with_id() {
+            if hp.0 == 0 {
+                ecs.commands().despawn(id);
+                ecs.commands().spawn_bundle(Hp(10));
+            }
+        }
+        assert_eq!(ecs.query::<&Hp>().iter_mut().count(), 2);
+    }
+
+    Ecs::apply_commands(&world);
+
+    let ecs = world.borrow();
+    let mut hps: Vec<u32> = ecs.query::<&Hp>().iter_mut().map(|hp| hp.0).collect();
+    hps.sort();
+    assert_eq!(hps, [5, 10]);
+    assert!(ecs.commands().is_empty());
+}
+
+#[test]
+fn components_are_inserted_and_removed_in_order() {
+    let world = world();
+    let target = world.borrow().query::<EntityId>().iter_mut().next().unwrap();
+    {
+        let ecs = world.borrow();
+        let mut commands = ecs.commands();
+        commands.insert(target, Dead);
+        commands.remove::<Hp>(target);
+        commands.spawn(|ecs, entity| ecs.insert_component(entity, Dead));
+    }
+
+    Ecs::apply_commands(&world);
+
+    let ecs = world.borrow();
+    assert!(ecs.get::<Hp>(target).is_none());
+    assert!(ecs.get::<Dead>(target).is_some());
+    assert_eq!(ecs.query_filtered::<EntityId, With<Dead>>().iter_mut().count(), 2);
+}
+
+#[test]
+fn commands_queued_while_applying_run_in_the_same_call() {
+    let world = world();
+    world.borrow().commands().add(|world| {
+        world.borrow().commands().spawn_bundle(Dead);
+    });
+
+    Ecs::apply_commands(&world);
+
+    assert_eq!(world.borrow().query::<&Dead>().iter_mut().count(), 1);
+}
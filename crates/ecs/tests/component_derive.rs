@@ -0,0 +1,50 @@
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(i32);
+
+#[derive(Component, Debug, PartialEq)]
+enum Team {
+    Red,
+    Blue,
+}
+
+#[derive(Component)]
+struct Marker;
+
+#[derive(Component)]
+struct Wrapper<T> {
+    value: T,
+}
+
+#[test]
+fn indexes_are_given_per_world_on_first_use() {
+    let mut first = Ecs::new();
+    let mut second = Ecs::new();
+    assert_eq!(first.component_index::<Pos>(), None);
+
+    let a = first.spawn((Pos(1), Team::Red));
+    let b = second.spawn((Team::Blue, Pos(2)));
+
+    assert_ne!(first.component_index::<Pos>(), second.component_index::<Pos>());
+    assert_eq!(first.component_index::<Pos>(), Some(first.register_component::<Pos>()));
+    assert_eq!(*first.get::<Team>(a).unwrap(), Team::Red);
+    assert_eq!(*second.get::<Pos>(b).unwrap(), Pos(2));
+}
+
+#[test]
+fn derived_types_keep_only_their_own_fields() {
+    assert_eq!(std::mem::size_of::<Marker>(), 0);
+    assert_eq!(std::mem::size_of::<Pos>(), std::mem::size_of::<i32>());
+
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn((
+        Marker,
+        Wrapper { value: 3_u8 },
+        Wrapper { value: "tank" },
+    ));
+
+    assert!(ecs.get::<Marker>(entity).is_some());
+    assert_eq!(ecs.get::<Wrapper<u8>>(entity).unwrap().value, 3);
+    assert_eq!(ecs.get::<Wrapper<&str>>(entity).unwrap().value, "tank");
+}
@@ -0,0 +1,54 @@
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(i32);
+
+#[test]
+fn queries_skip_disabled_entities() {
+    let mut ecs = Ecs::new();
+    let active = ecs.spawn(Pos(1));
+    let parked = ecs.spawn(Pos(2));
+
+    assert!(ecs.set_enabled(parked, false));
+    assert!(ecs.is_entity_alive(parked));
+    assert!(!ecs.is_entity_enabled(parked));
+
+    assert_eq!(ecs.query::<EntityId>().iter_mut().collect::<Vec<_>>(), [active]);
+    assert!(ecs.query::<&Pos>().get(parked).is_none());
+
+    let mut all: Vec<EntityId> = ecs.query_filtered::<EntityId, WithDisabled>().iter_mut().collect();
+    all.sort_by_key(|id| id.index());
+    assert_eq!(all, [active, parked]);
+}
+
+#[test]
+fn visit_skips_disabled_entities() {
+    let mut ecs = Ecs::new();
+    let parked = ecs.spawn(Pos(2));
+    ecs.set_enabled(parked, false);
+
+    let mut visited = false;
+    ecs.visit::<Pos>(parked, |_| visited = true);
+    assert!(!visited);
+}
+
+#[test]
+fn enabling_again_restores_the_entity_as_it_was() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Pos(3));
+    ecs.set_enabled(entity, false);
+    ecs.set_enabled(entity, true);
+
+    assert!(ecs.is_entity_enabled(entity));
+    assert_eq!(ecs.query::<&Pos>().single().map(|pos| pos.0), Some(3));
+}
+
+#[test]
+fn dead_entities_can_not_be_enabled() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Pos(0));
+    ecs.remove_entity(entity);
+
+    assert!(!ecs.set_enabled(entity, true));
+    assert!(!ecs.is_entity_enabled(entity));
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Speed(f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Health(u32);
+
+type Loader = fn(&str, &mut DynamicBundle) -> Option<()>;
+
+fn load_speed(value: &str, bundle: &mut DynamicBundle) -> Option<()> {
+    bundle.add(Speed(value.parse().ok()?));
+    Some(())
+}
+
+fn load_health(value: &str, bundle: &mut DynamicBundle) -> Option<()> {
+    bundle.add(Health(value.parse().ok()?));
+    Some(())
+}
+
+// Same shape as the game prefabs: components looked up by name at runtime.
+fn instantiate(components: &[(&str, &str)]) -> DynamicBundle {
+    let loaders: HashMap<&str, Loader> = HashMap::from([("speed", load_speed as Loader), ("health", load_health)]);
+
+    let mut bundle = DynamicBundle::new();
+    for (name, value) in components {
+        if let Some(loader) = loaders.get(name) {
+            loader(value, &mut bundle);
+        }
+    }
+    bundle
+}
+
+#[test]
+fn runtime_bundles_spawn_every_loaded_component() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(instantiate(&[("speed", "200"), ("health", "10")]));
+
+    assert_eq!(*ecs.get::<Speed>(entity).unwrap(), Speed(200.0));
+    assert_eq!(*ecs.get::<Health>(entity).unwrap(), Health(10));
+}
+
+#[test]
+fn unknown_and_invalid_components_are_left_out() {
+    let mut ecs = Ecs::new();
+    let bundle = instantiate(&[("speed", "fast"), ("shield", "1"), ("health", "3")]);
+    let entity = ecs.spawn(bundle);
+
+    assert!(ecs.get::<Speed>(entity).is_none());
+    assert_eq!(*ecs.get::<Health>(entity).unwrap(), Health(3));
+}
+
+#[test]
+fn empty_bundles_spawn_bare_entities() {
+    let mut ecs = Ecs::new();
+    let bundle = instantiate(&[]);
+    assert!(bundle.is_empty());
+
+    let entity = ecs.spawn(bundle);
+    assert!(ecs.is_entity_alive(entity));
+    assert!(ecs.get::<Health>(entity).is_none());
+}
+
+#[test]
+fn runtime_bundles_extend_existing_entities() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Health(1));
+
+    let mut bundle = DynamicBundle::new();
+    bundle.add((Speed(1.0), Health(2)));
+    ecs.insert_bundle(entity, bundle);
+
+    assert_eq!(*ecs.get::<Speed>(entity).unwrap(), Speed(1.0));
+    assert_eq!(*ecs.get::<Health>(entity).unwrap(), Health(2));
+}
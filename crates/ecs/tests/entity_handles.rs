@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ecs::*;
+
+static CLONES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Component, Debug, PartialEq)]
+struct Sprite(u32);
+
+impl Clone for Sprite {
+    fn clone(&self) -> Self {
+        CLONES.fetch_add(1, Ordering::Relaxed);
+        Self(self.0)
+    }
+}
+
+fn assert_copy<T: Copy + Send + Sync>() {}
+
+#[test]
+fn handles_are_plain_ids() {
+    assert_copy::<Entity>();
+
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Sprite(1));
+    let copy = entity;
+
+    assert_eq!(copy, entity);
+    assert_eq!(*ecs.get::<Sprite>(copy).unwrap(), Sprite(1));
+}
+
+#[test]
+fn borrowed_access_never_clones() {
+    let mut ecs = Ecs::new();
+    let entity = ecs.spawn(Sprite(1));
+
+    assert_eq!(ecs.get::<Sprite>(entity).unwrap().0, 1);
+    ecs.get_mut::<Sprite>(entity).unwrap().0 = 2;
+    assert_eq!(ecs.get::<Sprite>(entity).unwrap().0, 2);
+    assert_eq!(CLONES.load(Ordering::Relaxed), 0);
+
+    assert_eq!(ecs.get_component::<Sprite>(entity), Some(Sprite(2)));
+    assert_eq!(CLONES.load(Ordering::Relaxed), 1);
+}
@@ -0,0 +1,46 @@
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32);
+
+#[test]
+fn reused_indexes_get_a_new_generation() {
+    let mut ecs = Ecs::new();
+    let old = ecs.spawn(Pos(1.0));
+    ecs.remove_entity(old);
+    // The index is handed out again once its components are cleared.
+    ecs.process_self_events();
+    let new = ecs.spawn(Pos(2.0));
+
+    assert_eq!(new.index(), old.index());
+    assert_ne!(new.generation(), old.generation());
+    assert_ne!(new, old);
+}
+
+#[test]
+fn stale_ids_reach_nothing() {
+    let mut ecs = Ecs::new();
+    let old = ecs.spawn(Pos(1.0));
+    ecs.remove_entity(old);
+    ecs.process_self_events();
+    let new = ecs.spawn(Pos(2.0));
+
+    assert_eq!(new.index(), old.index());
+    assert!(!ecs.is_entity_alive(old));
+    assert!(ecs.get::<Pos>(old).is_none());
+    assert!(ecs.get_mut::<Pos>(old).is_none());
+    assert_eq!(*ecs.get::<Pos>(new).unwrap(), Pos(2.0));
+
+    // Despawning a stale id leaves the entity now using the index alone.
+    ecs.remove_entity(old);
+    assert!(ecs.is_entity_alive(new));
+}
+
+#[test]
+fn dangling_is_never_alive() {
+    let mut ecs = Ecs::new();
+    ecs.spawn(Pos(0.0));
+
+    assert!(!ecs.is_entity_alive(EntityId::DANGLING));
+    assert!(ecs.get::<Pos>(EntityId::DANGLING).is_none());
+}
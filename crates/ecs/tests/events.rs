@@ -0,0 +1,102 @@
+use ecs::*;
+
+#[derive(Debug, PartialEq)]
+struct Hit(u32);
+
+#[test]
+fn readers_see_each_event_once() {
+    let mut events = EventSystem::new();
+    let mut first = EventReader::<Hit>::new();
+    let mut second = EventReader::<Hit>::new();
+
+    events.push_event(Hit(1));
+    events.push_event(Hit(2));
+    assert_eq!(first.read(&events).collect::<Vec<_>>(), [&Hit(1), &Hit(2)]);
+
+    events.push_event(Hit(3));
+    assert_eq!(first.read(&events).collect::<Vec<_>>(), [&Hit(3)]);
+    assert!(first.is_empty(&events));
+
+    // Readers do not share a cursor.
+    assert_eq!(second.len(&events), 3);
+    second.clear(&events);
+    assert_eq!(second.read(&events).count(), 0);
+}
+
+#[test]
+fn events_last_two_updates() {
+    let mut events = EventSystem::new();
+    let mut reader = EventReader::<Hit>::new();
+
+    events.push_event(Hit(1));
+    events.update();
+    events.push_event(Hit(2));
+    assert_eq!(reader.len(&events), 2);
+
+    events.update();
+    assert_eq!(reader.read(&events).collect::<Vec<_>>(), [&Hit(2)]);
+
+    events.update();
+    assert!(events.get_events::<Hit>().unwrap().is_empty());
+}
+
+#[test]
+fn late_readers_start_at_the_current_events() {
+    let mut events = EventSystem::new();
+    events.push_event(Hit(1));
+
+    let mut reader = events.get_events::<Hit>().unwrap().get_reader();
+    events.push_event(Hit(2));
+
+    assert_eq!(reader.read(&events).collect::<Vec<_>>(), [&Hit(2)]);
+}
+
+#[derive(Component, Default)]
+struct Armor(u32);
+
+#[derive(Component, Default)]
+struct Health(u32);
+
+impl Listener<Hit> for Armor {
+    fn on_event(&mut self, event: &Hit) {
+        self.0 += event.0;
+    }
+}
+
+impl Listener<Hit> for Health {
+    fn on_event(&mut self, event: &Hit) {
+        self.0 += event.0 * 10;
+    }
+}
+
+#[test]
+fn entity_events_reach_every_listener_of_the_target_once() {
+    let mut ecs = Ecs::new();
+    ecs.register_listener::<Hit, Armor>();
+    ecs.register_listener::<Hit, Health>();
+    ecs.register_listener::<Hit, Armor>();
+    let target = ecs.spawn((Armor::default(), Health::default()));
+    let other = ecs.spawn((Armor::default(), Health::default()));
+
+    ecs.send_entity_event(target, Hit(1));
+    ecs.process_events_all::<Hit>();
+    ecs.process_events_all::<Hit>();
+
+    assert_eq!(ecs.get::<Armor>(target).unwrap().0, 1);
+    assert_eq!(ecs.get::<Health>(target).unwrap().0, 10);
+    assert_eq!(ecs.get::<Armor>(other).unwrap().0, 0);
+    assert_eq!(ecs.get::<Health>(other).unwrap().0, 0);
+}
+
+#[test]
+fn unregistered_listeners_are_skipped() {
+    let mut ecs = Ecs::new();
+    ecs.register_listener::<Hit, Armor>();
+    let target = ecs.spawn((Armor::default(), Health::default()));
+
+    ecs.send_entity_event(target, Hit(2));
+    ecs.process_events_all::<Hit>();
+
+    assert_eq!(ecs.get::<Armor>(target).unwrap().0, 2);
+    assert_eq!(ecs.get::<Health>(target).unwrap().0, 0);
+}
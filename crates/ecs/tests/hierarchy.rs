@@ -0,0 +1,73 @@
+use ecs::*;
+
+#[derive(Component)]
+struct Node;
+
+fn spawn(ecs: &mut Ecs, count: usize) -> Vec<EntityId> {
+    (0..count).map(|_| ecs.spawn(Node)).collect()
+}
+
+#[test]
+fn links_are_kept_on_both_sides() {
+    let mut ecs = Ecs::new();
+    let ids = spawn(&mut ecs, 3);
+    let (root, a, b) = (ids[0], ids[1], ids[2]);
+
+    assert!(ecs.set_parent(a, root));
+    assert!(ecs.set_parent(b, root));
+    assert_eq!(ecs.get_parent(a), Some(root));
+    assert_eq!(ecs.get_children(root), [a, b]);
+
+    // Reparenting leaves the old parent.
+    assert!(ecs.set_parent(b, a));
+    assert_eq!(ecs.get_children(root), [a]);
+    assert_eq!(ecs.get_children(a), [b]);
+
+    assert_eq!(ecs.remove_parent(b), Some(a));
+    assert!(ecs.get::<Children>(a).is_none());
+    assert_eq!(ecs.query_filtered::<EntityId, With<Parent>>().iter_mut().collect::<Vec<_>>(), [a]);
+}
+
+#[test]
+fn cycles_are_rejected() {
+    let mut ecs = Ecs::new();
+    let ids = spawn(&mut ecs, 3);
+    let (root, a, b) = (ids[0], ids[1], ids[2]);
+    ecs.set_parent(a, root);
+    ecs.set_parent(b, a);
+
+    assert!(!ecs.set_parent(root, b));
+    assert!(!ecs.set_parent(a, a));
+    assert_eq!(ecs.get_parent(root), None);
+    assert_eq!(ecs.get_parent(a), Some(root));
+}
+
+#[test]
+fn despawning_a_parent_despawns_the_subtree() {
+    let mut ecs = Ecs::new();
+    let ids = spawn(&mut ecs, 4);
+    let (root, a, b, other) = (ids[0], ids[1], ids[2], ids[3]);
+    ecs.set_parent(a, root);
+    ecs.set_parent(b, a);
+
+    ecs.remove_entity(root);
+
+    assert!(!ecs.is_entity_alive(root));
+    assert!(!ecs.is_entity_alive(a));
+    assert!(!ecs.is_entity_alive(b));
+    assert!(ecs.is_entity_alive(other));
+}
+
+#[test]
+fn despawning_a_child_leaves_the_parent() {
+    let mut ecs = Ecs::new();
+    let ids = spawn(&mut ecs, 3);
+    let (root, a, b) = (ids[0], ids[1], ids[2]);
+    ecs.set_parent(a, root);
+    ecs.set_parent(b, root);
+
+    ecs.remove_entity(a);
+
+    assert!(ecs.is_entity_alive(root));
+    assert_eq!(ecs.get_children(root), [b]);
+}
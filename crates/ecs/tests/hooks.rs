@@ -0,0 +1,75 @@
+use std::sync::{Arc, Mutex};
+
+use ecs::*;
+
+#[derive(Component)]
+struct Pos(i32);
+
+type Log = Arc<Mutex<Vec<&'static str>>>;
+
+fn world(log: &Log) -> Ecs {
+    let mut ecs = Ecs::new();
+    let hook = |name: &'static str| {
+        let log = log.clone();
+        move |_: &Ecs, _: EntityId| log.lock().unwrap().push(name)
+    };
+    ecs.register_component_hooks::<Pos>(
+        ComponentHooks::new()
+            .on_add(hook("add"))
+            .on_insert(hook("insert"))
+            .on_remove(hook("remove"))
+            .on_despawn(hook("despawn")),
+    );
+    ecs
+}
+
+#[test]
+fn inserting_runs_add_once_and_insert_every_time() {
+    let log = Log::default();
+    let mut ecs = world(&log);
+
+    let entity = ecs.spawn(Pos(0));
+    ecs.insert_component(entity, Pos(1));
+
+    assert_eq!(*log.lock().unwrap(), ["add", "insert", "insert"]);
+}
+
+#[test]
+fn removing_runs_remove_only() {
+    let log = Log::default();
+    let mut ecs = world(&log);
+
+    let entity = ecs.spawn(Pos(0));
+    log.lock().unwrap().clear();
+    ecs.remove_component::<Pos>(entity);
+    ecs.remove_component::<Pos>(entity);
+
+    assert_eq!(*log.lock().unwrap(), ["remove"]);
+}
+
+#[test]
+fn despawning_runs_despawn_then_remove() {
+    let log = Log::default();
+    let mut ecs = world(&log);
+
+    let entity = ecs.spawn(Pos(0));
+    log.lock().unwrap().clear();
+    ecs.remove_entity(entity);
+
+    assert_eq!(*log.lock().unwrap(), ["despawn", "remove"]);
+}
+
+#[test]
+fn despawn_hooks_still_reach_the_component() {
+    let seen = Arc::new(Mutex::new(None));
+    let value = seen.clone();
+    let mut ecs = Ecs::new();
+    ecs.register_component_hooks::<Pos>(ComponentHooks::new().on_despawn(move |ecs, entity| {
+        *value.lock().unwrap() = ecs.get::<Pos>(entity).map(|pos| pos.0);
+    }));
+
+    let entity = ecs.spawn(Pos(7));
+    ecs.remove_entity(entity);
+
+    assert_eq!(*seen.lock().unwrap(), Some(7));
+}
@@ -0,0 +1,49 @@
+use ecs::*;
+
+#[test]
+fn entities_are_found_by_name() {
+    let mut ecs = Ecs::new();
+    let player = ecs.spawn(Name::new("player 1"));
+
+    assert_eq!(ecs.find_by_name("player 1"), Some(player));
+    assert_eq!(ecs.find_by_name("player 2"), None);
+    assert_eq!(ecs.entity_label(player), "player 1");
+}
+
+#[test]
+fn renaming_and_despawning_update_the_index() {
+    let mut ecs = Ecs::new();
+    let player = ecs.spawn(Name::new("player 1"));
+
+    ecs.insert_component(player, Name::new("hero"));
+    assert_eq!(ecs.find_by_name("player 1"), None);
+    assert_eq!(ecs.find_by_name("hero"), Some(player));
+
+    ecs.remove_entity(player);
+    assert_eq!(ecs.find_by_name("hero"), None);
+}
+
+#[test]
+fn tags_list_enabled_entities_in_tagging_order() {
+    let mut ecs = Ecs::new();
+    let first = ecs.spawn(Tags::new(["enemy", "tank"]));
+    let parked = ecs.spawn(Tags::new(["enemy"]));
+    let last = ecs.spawn(Tags::new(["enemy", "enemy"]));
+    ecs.set_enabled(parked, false);
+
+    assert_eq!(ecs.iter_tagged("enemy").collect::<Vec<_>>(), [first, last]);
+    assert_eq!(ecs.iter_tagged("tank").collect::<Vec<_>>(), [first]);
+
+    ecs.remove_component::<Tags>(first);
+    assert_eq!(ecs.iter_tagged("tank").count(), 0);
+}
+
+#[test]
+fn disabled_entities_are_not_found() {
+    let mut ecs = Ecs::new();
+    let parked = ecs.spawn(Name::new("boss"));
+    ecs.set_enabled(parked, false);
+
+    assert_eq!(ecs.find_by_name("boss"), None);
+    assert_eq!(ecs.entity_label(parked), "boss");
+}
@@ -0,0 +1,91 @@
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Damage(u32);
+
+struct Bullet;
+struct Rocket;
+
+#[test]
+fn pooled_entities_are_reused_with_the_new_bundle() {
+    let mut ecs = Ecs::new();
+    let bullet = ecs.spawn_pooled::<Bullet>((Pos(0), Damage(1)));
+
+    assert!(ecs.despawn_pooled::<Bullet>(bullet));
+    assert_eq!(ecs.pooled_count::<Bullet>(), 1);
+    assert!(ecs.is_entity_alive(bullet));
+    assert!(ecs.query::<&Pos>().is_empty());
+
+    let reused = ecs.spawn_pooled::<Bullet>(Pos(5));
+    assert_eq!(reused, bullet);
+    assert_eq!(ecs.pooled_count::<Bullet>(), 0);
+    assert_eq!(*ecs.get::<Pos>(reused).unwrap(), Pos(5));
+    // Components outside the bundle are kept.
+    assert_eq!(*ecs.get::<Damage>(reused).unwrap(), Damage(1));
+}
+
+#[test]
+fn pools_are_kept_per_type() {
+    let mut ecs = Ecs::new();
+    let bullet = ecs.spawn_pooled::<Bullet>(Pos(0));
+    ecs.despawn_pooled::<Bullet>(bullet);
+
+    let rocket = ecs.spawn_pooled::<Rocket>(Pos(1));
+    assert_ne!(rocket, bullet);
+    assert_eq!(ecs.pooled_count::<Bullet>(), 1);
+}
+
+#[test]
+fn take_pooled_picks_a_matching_entity() {
+    let mut ecs = Ecs::new();
+    let weak = ecs.spawn_pooled::<Bullet>(Damage(1));
+    let strong = ecs.spawn_pooled::<Bullet>(Damage(5));
+    ecs.despawn_pooled::<Bullet>(weak);
+    ecs.despawn_pooled::<Bullet>(strong);
+
+    let taken = ecs.take_pooled::<Bullet>(|ecs, id| ecs.get::<Damage>(id).is_some_and(|damage| damage.0 == 1));
+    assert_eq!(taken, Some(weak));
+    assert!(ecs.is_entity_enabled(weak));
+    assert!(ecs.take_pooled::<Bullet>(|_, _| false).is_none());
+    assert_eq!(ecs.pooled_count::<Bullet>(), 1);
+}
+
+#[test]
+fn parking_twice_or_parking_dead_entities_is_refused() {
+    let mut ecs = Ecs::new();
+    let bullet = ecs.spawn_pooled::<Bullet>(Pos(0));
+    let dead = ecs.spawn(Pos(1));
+    ecs.remove_entity(dead);
+
+    assert!(ecs.despawn_pooled::<Bullet>(bullet));
+    assert!(!ecs.despawn_pooled::<Bullet>(bullet));
+    assert!(!ecs.despawn_pooled::<Bullet>(dead));
+    assert_eq!(ecs.pooled_count::<Bullet>(), 1);
+}
+
+#[test]
+fn clearing_a_pool_despawns_its_entities() {
+    let mut ecs = Ecs::new();
+    let bullet = ecs.spawn_pooled::<Bullet>(Pos(0));
+    ecs.despawn_pooled::<Bullet>(bullet);
+
+    ecs.clear_pool::<Bullet>();
+
+    assert!(!ecs.is_entity_alive(bullet));
+    assert_eq!(ecs.pooled_count::<Bullet>(), 0);
+}
+
+#[test]
+fn batches_spawn_and_despawn_together() {
+    let mut ecs = Ecs::new();
+    let ids = ecs.spawn_batch((0..3).map(|i| (Pos(i), Damage(i as u32))));
+    assert_eq!(ids.len(), 3);
+    assert_eq!(ecs.get::<Pos>(ids[2]).unwrap().0, 2);
+
+    ecs.despawn_batch([ids[0], ids[2], ids[0]]);
+
+    assert_eq!(ecs.query::<EntityId>().iter_mut().collect::<Vec<_>>(), [ids[1]]);
+}
@@ -0,0 +1,92 @@
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Vel(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Frozen;
+
+#[derive(Component)]
+struct Unused;
+
+fn world() -> (Ecs, [EntityId; 3]) {
+    let mut ecs = Ecs::new();
+    let moving = ecs.spawn((Pos(0), Vel(1)));
+    let frozen = ecs.spawn((Pos(10), Vel(2), Frozen));
+    let still = ecs.spawn(Pos(20));
+    (ecs, [moving, frozen, still])
+}
+
+#[test]
+fn tuples_match_entities_with_every_component() {
+    let (ecs, _) = world();
+
+    for (mut pos, vel) in ecs.query::<(&mut Pos, &Vel)>().iter_mut() {
+        pos.0 += vel.0;
+    }
+
+    let mut positions: Vec<i32> = ecs.query::<&Pos>().iter_mut().map(|pos| pos.0).collect();
+    positions.sort();
+    assert_eq!(positions, [1, 12, 20]);
+}
+
+#[test]
+fn filters_narrow_the_matches() {
+    let (ecs, [moving, frozen, still]) = world();
+
+    let ids: Vec<EntityId> = ecs.query_filtered::<EntityId, (With<Vel>, Without<Frozen>)>().iter_mut().collect();
+    assert_eq!(ids, [moving]);
+
+    let mut ids: Vec<EntityId> = ecs.query_filtered::<EntityId, Without<Vel>>().iter_mut().collect();
+    ids.sort_by_key(|id| id.index());
+    assert_eq!(ids, [still]);
+
+    assert!(ecs.query::<(&Pos, &Frozen)>().get(frozen).is_some());
+    assert!(ecs.query::<(&Pos, &Frozen)>().get(moving).is_none());
+}
+
+#[test]
+fn optional_components_do_not_filter() {
+    let (ecs, [moving, frozen, still]) = world();
+
+    let mut items: Vec<(EntityId, Option<i32>)> = ecs
+        .query::<(EntityId, Option<&Vel>)>()
+        .iter_mut()
+        .map(|(id, vel)| (id, vel.map(|vel| vel.0)))
+        .collect();
+    items.sort_by_key(|(id, _)| id.index());
+    assert_eq!(items, [(moving, Some(1)), (frozen, Some(2)), (still, None)]);
+}
+
+#[test]
+fn iterators_stop_early_and_carry_ids() {
+    let (ecs, _) = world();
+
+    let first = ecs.query::<&Pos>().iter_mut().find(|pos| pos.0 >= 10).map(|pos| pos.0);
+    assert!(first.is_some());
+
+    for (id, pos) in ecs.query::<&Pos>().iter_with_id() {
+        assert_eq!(ecs.get::<Pos>(id).unwrap().0, pos.0);
+    }
+
+    assert!(ecs.query_filtered::<&Pos, With<Frozen>>().single().is_some());
+    assert!(ecs.query::<&Pos>().single().is_none());
+}
+
+#[test]
+fn unknown_components_match_nothing() {
+    let (ecs, _) = world();
+
+    assert!(ecs.query::<(&Pos, &Unused)>().is_empty());
+    assert_eq!(ecs.query_filtered::<&Pos, Without<Unused>>().iter_mut().count(), 3);
+}
+
+#[test]
+#[should_panic(expected = "more than once")]
+fn writing_a_component_twice_panics() {
+    let (ecs, _) = world();
+    ecs.query::<(&mut Pos, &Pos)>();
+}
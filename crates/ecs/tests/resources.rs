@@ -0,0 +1,38 @@
+use ecs::*;
+
+struct Clock(f32);
+
+#[derive(Debug, PartialEq)]
+struct Score(u32);
+
+#[test]
+fn resources_are_stored_by_type() {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Clock(0.5));
+    ecs.insert_resource(Score(1));
+
+    assert!(ecs.has_resource::<Clock>());
+    assert_eq!(ecs.resource::<Clock>().unwrap().0, 0.5);
+    assert_eq!(*ecs.resource::<Score>().unwrap(), Score(1));
+}
+
+#[test]
+fn resources_are_written_through_a_shared_world() {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Score(1));
+
+    ecs.resource_mut::<Score>().unwrap().0 += 2;
+    assert_eq!(*ecs.resource::<Score>().unwrap(), Score(3));
+}
+
+#[test]
+fn inserting_again_replaces_and_removing_hands_back() {
+    let mut ecs = Ecs::new();
+    ecs.insert_resource(Score(1));
+    ecs.insert_resource(Score(7));
+
+    assert_eq!(ecs.remove_resource::<Score>(), Some(Score(7)));
+    assert!(!ecs.has_resource::<Score>());
+    assert!(ecs.resource::<Score>().is_none());
+    assert!(ecs.resource_mut::<Clock>().is_none());
+}
@@ -0,0 +1,113 @@
+use std::sync::{Arc, Mutex};
+
+use ecs::*;
+
+type Log = Arc<Mutex<Vec<&'static str>>>;
+
+fn logger(log: &Log, name: &'static str) -> SystemConfig {
+    let log = log.clone();
+    SystemConfig::new(move |_: &EcsRc| log.lock().unwrap().push(name)).label(name)
+}
+
+fn world() -> EcsRc {
+    Arc::new(EcsLock::new(Ecs::new()))
+}
+
+#[test]
+fn stages_run_in_order_whatever_the_insertion_order() {
+    let log = Log::default();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Render, logger(&log, "draw"))
+        .add_system(Stage::Cleanup, logger(&log, "cleanup"))
+        .add_system(Stage::Input, logger(&log, "input"))
+        .add_system(Stage::Simulation, logger(&log, "move"));
+
+    schedule.run(&world());
+
+    assert_eq!(*log.lock().unwrap(), ["input", "move", "cleanup", "draw"]);
+}
+
+#[test]
+fn before_and_after_order_systems_in_a_stage() {
+    let log = Log::default();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Simulation, logger(&log, "fire").after("move"))
+        .add_system(Stage::Simulation, logger(&log, "ai").before("move"))
+        .add_system(Stage::Simulation, logger(&log, "move"));
+
+    schedule.run(&world());
+
+    assert_eq!(*log.lock().unwrap(), ["ai", "move", "fire"]);
+}
+
+#[test]
+fn broken_orderings_are_reported() {
+    let log = Log::default();
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Simulation, logger(&log, "a").after("b"))
+        .add_system(Stage::Simulation, logger(&log, "b").after("a"));
+    assert!(matches!(schedule.build(), Err(ScheduleError::Cycle(_))));
+
+    let mut schedule = Schedule::new();
+    schedule.add_system(Stage::Simulation, logger(&log, "a").after("missing"));
+    assert!(matches!(schedule.build(), Err(ScheduleError::UnknownLabel { .. })));
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Simulation, logger(&log, "a"))
+        .add_system(Stage::Render, logger(&log, "a"));
+    assert_eq!(schedule.build(), Err(ScheduleError::DuplicateLabel("a".to_string())));
+}
+
+struct Paused;
+
+#[test]
+fn run_conditions_skip_systems() {
+    let log = Log::default();
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(Stage::Simulation, logger(&log, "move").run_if(|ecs| !ecs.has_resource::<Paused>()))
+        .add_system(Stage::Render, logger(&log, "draw"));
+
+    let world = world();
+    world.borrow_mut().insert_resource(Paused);
+    schedule.run(&world);
+    world.borrow_mut().remove_resource::<Paused>();
+    schedule.run(&world);
+
+    assert_eq!(*log.lock().unwrap(), ["draw", "move", "draw"]);
+}
+
+#[derive(Component)]
+struct Spawned;
+
+#[test]
+fn commands_are_applied_between_stages() {
+    let seen = Arc::new(Mutex::new(0));
+    let counter = seen.clone();
+
+    let mut schedule = Schedule::new();
+    schedule
+        .add_system(
+            Stage::Simulation,
+            SystemConfig::new(|world: &EcsRc| {
+                world.borrow().commands().spawn_bundle(Spawned);
+            })
+            .label("spawn"),
+        )
+        .add_system(
+            Stage::Cleanup,
+            SystemConfig::new(move |world: &EcsRc| {
+                *counter.lock().unwrap() = world.borrow().query::<&Spawned>().iter_mut().count();
+            })
+            .label("count"),
+        );
+
+    schedule.run(&world());
+
+    assert_eq!(*seen.lock().unwrap(), 1);
+}
@@ -0,0 +1,113 @@
+use std::sync::Arc;
+
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(f32, f32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Target(EntityId);
+
+impl Serializable for Pos {
+    const NAME: &'static str = "pos";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.0);
+        writer.write_f32(self.1);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self(reader.read_f32()?, reader.read_f32()?))
+    }
+}
+
+impl Serializable for Target {
+    const NAME: &'static str = "target";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_entity(self.0);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self(reader.read_entity()?))
+    }
+}
+
+impl MapEntities for Target {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.0 = map.remap(self.0);
+    }
+}
+
+struct Bullet;
+
+fn registry() -> SnapshotRegistry {
+    SnapshotRegistry::new().register::<Pos>().register::<Target>()
+}
+
+fn empty_world() -> EcsRc {
+    let mut ecs = Ecs::new();
+    ecs.register_map_entities::<Target>();
+    Arc::new(EcsLock::new(ecs))
+}
+
+#[test]
+fn worlds_round_trip_with_remapped_references() {
+    let mut ecs = Ecs::new();
+    let gap = ecs.spawn(Pos(0.0, 0.0));
+    let tank = ecs.spawn(Pos(1.5, -2.0));
+    let turret = ecs.spawn((Pos(0.0, 1.0), Target(tank)));
+    let ghost = ecs.spawn(Pos(9.0, 9.0));
+    ecs.set_parent(turret, tank);
+    ecs.set_enabled(ghost, false);
+    ecs.remove_entity(gap);
+    let data = registry().save(&ecs);
+
+    let world = empty_world();
+    // Occupied indexes force the loaded entities onto new ids.
+    world.borrow_mut().spawn(Pos(5.0, 5.0));
+    let map = registry().load(&world, &data).unwrap();
+    assert_eq!(map.len(), 3);
+
+    let ecs = world.borrow();
+    let (new_tank, new_turret, new_ghost) = (map.remap(tank), map.remap(turret), map.remap(ghost));
+    assert_eq!(*ecs.get::<Pos>(new_tank).unwrap(), Pos(1.5, -2.0));
+    assert_eq!(ecs.get::<Target>(new_turret).unwrap().0, new_tank);
+    assert_eq!(ecs.get_parent(new_turret), Some(new_tank));
+    assert!(!ecs.is_entity_enabled(new_ghost));
+    assert_eq!(map.get(gap), None);
+}
+
+#[test]
+fn pooled_entities_are_not_saved() {
+    let mut ecs = Ecs::new();
+    ecs.spawn(Pos(0.0, 0.0));
+    let parked = ecs.spawn_pooled::<Bullet>(Pos(1.0, 1.0));
+    ecs.despawn_pooled::<Bullet>(parked);
+
+    let map = registry().load(&empty_world(), &registry().save(&ecs)).unwrap();
+
+    assert_eq!(map.len(), 1);
+    assert_eq!(map.get(parked), None);
+}
+
+#[test]
+fn broken_snapshots_are_rejected_and_leave_nothing_behind() {
+    let world = empty_world();
+
+    assert_eq!(registry().load(&world, "entity 0:0").unwrap_err(), SnapshotError::MissingHeader);
+    assert_eq!(
+        registry().load(&world, "ecs-snapshot 99").unwrap_err(),
+        SnapshotError::UnsupportedVersion(99)
+    );
+    assert!(matches!(
+        registry().load(&world, "ecs-snapshot 2\nentity 0:0\ncomponent shield 1"),
+        Err(SnapshotError::UnknownComponent { line: 3, .. })
+    ));
+    assert!(matches!(
+        registry().load(&world, "ecs-snapshot 2\nentity 0:0\nentity 1:0\ncomponent pos x"),
+        Err(SnapshotError::InvalidLine { line: 4, .. })
+    ));
+
+    assert_eq!(world.borrow().query::<EntityId>().iter_mut().count(), 0);
+}
@@ -0,0 +1,62 @@
+use ecs::*;
+
+#[derive(Component, Debug, PartialEq)]
+struct Pos(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Burning(u32);
+
+fn world() -> Ecs {
+    let mut ecs = Ecs::new();
+    ecs.register_component_storage::<Burning>(StorageType::SparseSet);
+    ecs
+}
+
+#[test]
+fn components_are_tables_unless_registered_sparse() {
+    let mut ecs = world();
+    ecs.spawn((Pos(0), Burning(1)));
+
+    assert_eq!(ecs.storage_type::<Pos>(), Some(StorageType::Table));
+    assert_eq!(ecs.storage_type::<Burning>(), Some(StorageType::SparseSet));
+}
+
+#[test]
+fn sparse_components_do_not_move_table_rows() {
+    let mut ecs = world();
+    let entity = ecs.spawn(Pos(1));
+    let location = ecs.archetypes().location(entity.index());
+    let archetypes = ecs.archetypes().len();
+
+    ecs.insert_component(entity, Burning(3));
+    assert_eq!(ecs.archetypes().location(entity.index()), location);
+    assert_eq!(*ecs.get::<Burning>(entity).unwrap(), Burning(3));
+
+    assert_eq!(ecs.remove_component::<Burning>(entity), Some(Burning(3)));
+    assert_eq!(ecs.archetypes().location(entity.index()), location);
+    assert_eq!(ecs.archetypes().len(), archetypes);
+    assert_eq!(*ecs.get::<Pos>(entity).unwrap(), Pos(1));
+}
+
+#[test]
+fn queries_mix_sparse_and_table_components() {
+    let mut ecs = world();
+    let burning = ecs.spawn((Pos(1), Burning(2)));
+    ecs.spawn(Pos(3));
+    ecs.spawn(Burning(4));
+
+    let items: Vec<(EntityId, i32, u32)> = ecs
+        .query::<(EntityId, &Pos, &Burning)>()
+        .iter_mut()
+        .map(|(id, pos, burning)| (id, pos.0, burning.0))
+        .collect();
+    assert_eq!(items, [(burning, 1, 2)]);
+    assert_eq!(ecs.query_filtered::<&Pos, Without<Burning>>().iter_mut().count(), 1);
+}
+
+#[test]
+#[should_panic(expected = "can not change")]
+fn storage_types_are_fixed_by_the_first_registration() {
+    let mut ecs = world();
+    ecs.register_component_storage::<Burning>(StorageType::Table);
+}
@@ -0,0 +1,80 @@
+use ecs::*;
+
+#[derive(Component, Clone, Debug, PartialEq)]
+struct Pos(i32);
+
+#[derive(Component, Debug, PartialEq)]
+struct Target(EntityId);
+
+impl MapEntities for Target {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.0 = map.remap(self.0);
+    }
+}
+
+fn world() -> Ecs {
+    let mut ecs = Ecs::new();
+    ecs.register_clone::<Pos>();
+    ecs.register_map_entities::<Target>();
+    ecs
+}
+
+#[test]
+fn worlds_have_their_own_ids() {
+    assert_ne!(Ecs::new().id(), Ecs::new().id());
+}
+
+#[test]
+fn moving_takes_the_whole_subtree() {
+    let mut src = world();
+    let mut dst = world();
+    let outsider = src.spawn(Pos(0));
+    let tank = src.spawn(Pos(1));
+    let turret = src.spawn((Pos(2), Target(tank)));
+    let radar = src.spawn(Target(outsider));
+    src.set_parent(turret, tank);
+    src.set_parent(radar, turret);
+    src.set_enabled(radar, false);
+
+    let map = src.move_entity(tank, &mut dst).unwrap();
+
+    assert!(!src.is_entity_alive(tank));
+    assert!(!src.is_entity_alive(turret));
+    assert!(src.is_entity_alive(outsider));
+
+    let (tank, turret, radar) = (map.remap(tank), map.remap(turret), map.remap(radar));
+    assert_eq!(*dst.get::<Pos>(tank).unwrap(), Pos(1));
+    assert_eq!(dst.get_parent(turret), Some(tank));
+    assert_eq!(dst.get_parent(radar), Some(turret));
+    assert!(!dst.is_entity_enabled(radar));
+    // References are remapped, ones that leave the moved entities dangle.
+    assert_eq!(dst.get::<Target>(turret).unwrap().0, tank);
+    assert_eq!(dst.get::<Target>(radar).unwrap().0, EntityId::DANGLING);
+}
+
+#[test]
+fn cloning_copies_only_clone_components() {
+    let mut src = world();
+    let mut dst = world();
+    let tank = src.spawn((Pos(1), Target(EntityId::DANGLING)));
+
+    let map = src.clone_entity(tank, &mut dst).unwrap();
+
+    let copy = map.remap(tank);
+    assert_eq!(*dst.get::<Pos>(copy).unwrap(), Pos(1));
+    assert!(dst.get::<Target>(copy).is_none());
+    assert_eq!(*src.get::<Pos>(tank).unwrap(), Pos(1));
+    assert!(src.get::<Target>(tank).is_some());
+}
+
+#[test]
+fn dead_entities_are_not_transferred() {
+    let mut src = world();
+    let mut dst = world();
+    let tank = src.spawn(Pos(1));
+    src.remove_entity(tank);
+
+    assert!(src.move_entity(tank, &mut dst).is_none());
+    assert!(src.clone_entity(tank, &mut dst).is_none());
+    assert!(dst.query::<EntityId>().is_empty());
+}
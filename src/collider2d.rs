@@ -1,7 +1,6 @@
 use crate::bounds::Bounds;
 use crate::quad_tree::Handle;
 use ::ecs::*;
-use ecs_derive::component_impl;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CollisionEvent {
@@ -9,8 +8,7 @@ pub enum CollisionEvent {
     OnBorder,
 }
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct Collider2d {
     bounds: Bounds,
     area_handle: Option<Handle>,
@@ -19,9 +17,8 @@ pub struct Collider2d {
 }
 
 impl Collider2d {
-    pub fn new(entity: &EntityWeak, bounds: Bounds) -> Self {
+    pub fn new(bounds: Bounds) -> Self {
         Self {
-            entity: entity.clone(),
             bounds,
             area_handle: None,
             reached_border: false,
@@ -29,9 +26,8 @@ impl Collider2d {
         }
     }
 
-    pub fn with_ignore(entity: &EntityWeak, bounds: Bounds, ignores: Vec<EntityId>) -> Self {
+    pub fn with_ignore(bounds: Bounds, ignores: Vec<EntityId>) -> Self {
         Self {
-            entity: entity.clone(),
             bounds,
             area_handle: None,
             reached_border: false,
@@ -70,3 +66,54 @@ impl Collider2d {
         self.area_handle
     }
 }
+
+// The quad tree handle is not saved, restored colliders have to be placed again.
+impl Serializable for Collider2d {
+    const NAME: &'static str = "collider2d";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        let (x, y) = self.bounds.get_position();
+        let (width, height) = self.bounds.get_size();
+        writer.write_f32(x);
+        writer.write_f32(y);
+        writer.write_f32(width);
+        writer.write_f32(height);
+        writer.write_bool(self.reached_border);
+
+        let ignores = self.collision_ignore.as_deref().unwrap_or(&[]);
+        writer.write_u32(ignores.len() as u32);
+        for id in ignores.iter() {
+            writer.write_entity(*id);
+        }
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        let bounds = Bounds::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+        let reached_border = reader.read_bool()?;
+
+        let count = reader.read_u32()?;
+        let mut ignores = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            ignores.push(reader.read_entity()?);
+        }
+
+        let mut collider = if ignores.is_empty() {
+            Self::new(bounds)
+        } else {
+            Self::with_ignore(bounds, ignores)
+        };
+        collider.reached_border = reached_border;
+
+        Some(collider)
+    }
+}
+
+// The handle points into the quad tree of the old world.
+impl MapEntities for Collider2d {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.area_handle = None;
+        if let Some(ignores) = self.collision_ignore.as_mut() {
+            ignores.iter_mut().for_each(|id| *id = map.remap(*id));
+        }
+    }
+}
@@ -3,78 +3,143 @@
 
 use crate::bounds::Bounds;
 use crate::collider2d::{Collider2d, CollisionEvent};
-use crate::system::ai_system::AiSystem;
+use crate::system::ai_system::{self, AiSystem};
 use crate::system::fire_system::FireSystem;
 use crate::quad_tree::QuadTree;
+use crate::system::bullet_system::BulletSystem;
 use crate::system::move_system::MoveSystem;
-use crate::system::system_trait::System;
+use crate::system::transform_system::TransformSystem;
 use glfw::{Action, Key};
-use rand::rngs::ThreadRng;
-use std::cell::RefCell;
-use std::ops::Deref;
-use std::rc::{Rc, Weak};
-use std::vec::Vec;
+use std::sync::Arc;
 use fxhash::FxHashMap;
+use rand::{rngs::StdRng, SeedableRng};
 use crate::map::Map;
 use crate::object_components::{Bullet, Damagable, Gun, Lifetime, Movable};
 use crate::render::Drawable;
 use crate::render::Render;
+use crate::resources::{GameClock, Score};
 use crate::sprite::Sprite;
-use crate::transform::Transform;
+use crate::transform::{GlobalTransform, Transform};
+use crate::prefab::PrefabRegistry;
 use crate::player_config::{Player, PlayerAction, PlayerController, PlayerState};
 use ::ecs::*;
-use ecs_derive::component_impl;
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct InputLayoutComponent {
     key_actions: FxHashMap<Key, PlayerAction>,
 }
 
 impl InputLayoutComponent {
-    pub fn new(entity: EntityWeak, actions: FxHashMap<Key, PlayerAction>) -> Self {
-        Self{ key_actions: actions, entity: entity }
+    pub fn new(actions: FxHashMap<Key, PlayerAction>) -> Self {
+        Self{ key_actions: actions }
     }
 
-    fn do_input(&mut self, event: &glfw::WindowEvent) {
+    fn do_input(&self, event: &glfw::WindowEvent) -> Option<PlayerAction> {
         if let glfw::WindowEvent::Key(in_key, _, action, _) = event {
             if let Some(item) = self.key_actions.get(in_key) {
-                if let Some(entity) = self.entity.upgrade() {
-                    if *action == Action::Press || *action == Action::Repeat {
-                        entity.push_event(*item);
-                    }
-                    else {
-                        entity.push_event(PlayerAction::None);
-                    }
+                if *action == Action::Press || *action == Action::Repeat {
+                    return Some(*item);
                 }
+                return Some(PlayerAction::None);
             }
         }
+        None
     }
 }
 
+fn process_player_actions(world: &EcsRc) {
+    let ecs = world.borrow();
+    ecs.process_events_all::<PlayerAction>();
+}
+
+fn process_collisions(world: &EcsRc) {
+    let ecs = world.borrow();
+    ecs.process_events_all::<CollisionEvent>();
+}
+
+// Bullets are checked against the tree, they never take a place in it.
+fn add_to_spatial_index(ecs: &Ecs, entity_id: EntityId) {
+    if ecs.get::<Bullet>(entity_id).is_some() {
+        return;
+    }
+    if let Some(quad_tree) = ecs.resource::<QuadTree>() {
+        quad_tree.place(ecs, entity_id);
+    }
+}
+
+fn remove_from_spatial_index(ecs: &Ecs, entity_id: EntityId) {
+    if let Some(quad_tree) = ecs.resource::<QuadTree>() {
+        quad_tree.on_entity_removed(entity_id);
+    }
+}
+
+pub fn snapshot_registry() -> SnapshotRegistry {
+    SnapshotRegistry::new()
+        .register::<Transform>()
+        .register::<GlobalTransform>()
+        .register::<Sprite>()
+        // Bullet goes before the collider, the placement hook skips bullets.
+        .register::<Bullet>()
+        .register::<Collider2d>()
+        .register::<Movable>()
+        .register::<Damagable>()
+        .register::<Gun>()
+        .register::<Lifetime>()
+        .register::<PlayerController>()
+        .register::<ai_system::AiController>()
+        .register::<Name>()
+        .register::<Tags>()
+}
+
+fn create_world(width: u32, height: u32) -> Ecs {
+    let mut world = Ecs::new();
+    world.insert_resource(QuadTree::new(Bounds::new(0_f32, 0_f32, width as f32, height as f32)));
+    world.insert_resource(GameClock::new());
+    world.insert_resource(Score::new());
+    world.insert_resource(StdRng::from_os_rng());
+
+    let mut prefabs = PrefabRegistry::new();
+    prefabs.register_loader("ai_controller", ai_system::load_ai_controller);
+    prefabs.load("prefabs.yaml");
+    world.insert_resource(prefabs);
+
+    // Few entities carry these, packed storage keeps their queries short.
+    world.register_component_storage::<Bullet>(StorageType::SparseSet);
+    world.register_component_storage::<ai_system::AiController>(StorageType::SparseSet);
+
+    world.register_listener::<PlayerAction, PlayerController>();
+    world.register_listener::<PlayerAction, Gun>();
+    world.register_listener::<CollisionEvent, Bullet>();
+    world.register_component_hooks::<Collider2d>(
+        ComponentHooks::new()
+            .on_add(add_to_spatial_index)
+            .on_remove(remove_from_spatial_index),
+    );
+    world.register_map_entities::<Bullet>();
+    world.register_map_entities::<Collider2d>();
+
+    world
+}
+
 pub struct Game {
-    rnd: ThreadRng,
     world: EcsRc,
+    width: u32,
+    height: u32,
     players: [Option<Player>; 2],
     map: Map,
-    bullets: RefCell<Vec<Box<dyn FnOnce()>>>,
-    frame_counter: u32,
-    quad_tree: Rc<RefCell<QuadTree>>,
     // fire_system: FireSystem,
-    systems: Vec<Box<dyn System>>,
+    schedule: Schedule,
 }
 
 impl Game {
     pub fn new(width: u32, height: u32) -> Self {
         Self {
-            rnd: rand::rng(),
-            world: Rc::new(RefCell::new(Ecs::new())),
+            world: Arc::new(EcsLock::new(create_world(width, height))),
+            width,
+            height,
             players: [None, None],
             map: Map::new(width, height),
-            bullets: RefCell::new(Vec::new()),
-            frame_counter: 0,
-            quad_tree: Rc::new(RefCell::new(QuadTree::new(Bounds::new(0_f32, 0_f32, width as f32, height as f32)))),
-            systems: Vec::new(),
+            schedule: Schedule::new(),
         }
     }
 
@@ -85,14 +150,26 @@ impl Game {
         index: u32,
     ) -> Option<Player> {
         
-        Player::new(&self.world, index, config, self.quad_tree.borrow_mut(), &render)
+        Player::new(&self.world, index, config, render)
     }
 
     pub fn init(&mut self, render: &mut Render) {
 
-        self.systems.push(Box::new(MoveSystem::new(self.quad_tree.clone())));
-        self.systems.push(Box::new(FireSystem::new()));
-        self.systems.push(Box::new(AiSystem::new(self.quad_tree.clone())));
+        self.schedule
+            .add_system(Stage::Input, SystemConfig::new(process_player_actions).label("player_actions"))
+            .add_system(Stage::Simulation, SystemConfig::new(MoveSystem::new()).label("move"))
+            .add_system(Stage::Simulation, SystemConfig::new(FireSystem::new()).label("fire").after("move"))
+            .add_system(Stage::Simulation, SystemConfig::new(AiSystem::new()).label("ai"))
+            .add_system(Stage::Collision, SystemConfig::new(process_collisions).label("collisions"))
+            .add_system(
+                Stage::Cleanup,
+                SystemConfig::new(BulletSystem::new())
+                    .label("bullets")
+                    .run_if(|ecs| ecs.is_componet_exist::<Bullet>()),
+            )
+            .add_system(Stage::Render, SystemConfig::new(TransformSystem::new()).label("transforms"));
+        self.schedule.build().expect("Invalid system schedule");
+
         self.players[0] = self.create_player(
             render,
             "player1.yaml",
@@ -106,63 +183,91 @@ impl Game {
         );
     }
 
-    fn bullet_system_update(&self, dt: f32) {
-        let ecs = self.world.deref().borrow();
-        if ecs.is_componet_exist::<Bullet>() {
-            ecs.visit_all3::<Lifetime, Movable, Collider2d>(|lifetime, movable, collider| {
-                lifetime.update(dt);
-
-                if collider.is_reached_border() || lifetime.is_time_out() {
-                    if let Some(entity) = collider.entity.upgrade() {
-                        ecs.remove_entity(entity.get_id());
-                    }
-                } else {
-                    movable.set_dirty(true);
-                }
-            });
+    pub fn update(&mut self, dt: f32) {
+        //self.map.update(&self.world);
+        self.world.borrow().resource_mut::<GameClock>().unwrap().tick(dt);
+        self.schedule.run(&self.world);
+    }
+
+    pub fn do_input(&mut self, event: &glfw::WindowEvent) {
+        let ecs = self.world.borrow();
+        for (id, input_component) in ecs.query::<&InputLayoutComponent>().iter_with_id() {
+            if let Some(action) = input_component.do_input(event) {
+                ecs.send_entity_event(id, action);
+            }
         }
     }
 
-    pub fn update(&mut self, dt: f32) {
-        //self.map.update(&self.world);
-        for s in self.systems.iter_mut() {
-            s.update(&self.world, dt);
+    pub fn save_snapshot(&self, path: &str) -> bool {
+        let data = snapshot_registry().save(&self.world.borrow());
+        match std::fs::write(path, data) {
+            Ok(_) => true,
+            Err(err) => {
+                println!("Failed to save snapshot {}: {}", path, err);
+                false
+            }
         }
-        self.process_events();
-        self.bullet_system_update(dt);
-        //self.fire_system.update(&self.world, dt); 
-        //self.move_system_update(dt);
-        let mut ecs = self.world.borrow_mut();
-        ecs.process_self_events();
-        self.frame_counter += 1;
     }
 
-    pub fn process_events(&self) {
-        let ecs = self.world.borrow();
-         ecs.process_events::<PlayerAction, PlayerController>();
-         ecs.process_events::<PlayerAction, Gun>();
-         ecs.clean_events::<PlayerAction>();
+    // Restores into a fresh world so nothing from the current round leaks in.
+    pub fn load_snapshot(&mut self, path: &str) -> bool {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) => {
+                println!("Failed to read snapshot {}: {}", path, err);
+                return false;
+            }
+        };
+
+        let world: EcsRc = Arc::new(EcsLock::new(create_world(self.width, self.height)));
+        if let Err(err) = snapshot_registry().load(&world, &data) {
+            println!("Failed to load snapshot {}: {}", path, err);
+            return false;
+        }
+
+        {
+            let mut ecs = world.borrow_mut();
+            let players: Vec<(EntityId, u32)> = ecs
+                .query::<&PlayerController>()
+                .iter_with_id()
+                .map(|(id, controller)| (id, controller.player_index))
+                .collect();
+            for (id, index) in players {
+                if let Some(player) = self.players.get_mut(index as usize).and_then(|p| p.as_mut()) {
+                    player.attach(&mut ecs, id);
+                }
+            }
+        }
 
-         ecs.process_events::<CollisionEvent, Bullet>(); 
+        self.world = world;
+        true
     }
 
-    pub fn do_input(&mut self, event: &glfw::WindowEvent) {
-        let ecs = self.world.borrow_mut();
-        ecs.visit_all::<InputLayoutComponent>(|input_component| {
-            input_component.do_input(event);
-        });
+    // Player tanks keep their state and children, the rest of the stage is dropped.
+    pub fn next_stage(&mut self) {
+        let mut stage = create_world(self.width, self.height);
+        {
+            let mut ecs = self.world.borrow_mut();
+            for player in self.players.iter_mut().flatten() {
+                if let Some(map) = ecs.move_entity(player.get_player_entity(), &mut stage) {
+                    let entity = map.remap(player.get_player_entity());
+                    player.attach(&mut stage, entity);
+                }
+            }
+        }
+
+        self.world = Arc::new(EcsLock::new(stage));
     }
 
     pub fn do_draw(&mut self, render: &mut Render) {
         let ecs = self.world.borrow();
 
-        ecs.visit_all2::<Transform, Sprite>(|transform, sprite| {
+        for (transform, mut sprite) in ecs.query::<(&GlobalTransform, &mut Sprite)>().iter_mut() {
             if !sprite.is_initizlized() {
                 sprite.init(render);
             }
 
-            transform.apply_transform_changes();
-            sprite.draw(render, &transform.transform);
-        });
+            sprite.draw(render, transform.get_matrix());
+        }
     }
 }
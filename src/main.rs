@@ -8,8 +8,10 @@ mod game;
 mod gl_wrappers;
 mod map;
 mod object_components;
+mod prefab;
 mod quad_tree;
 mod render;
+mod resources;
 mod sprite;
 mod transform;
 mod player_config;
@@ -70,8 +72,18 @@ fn main() {
         glfw.poll_events();
         for (_, event) in glfw::flush_messages(&events) {
             game.do_input(&event);
-            if let glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) = event {
-                window.set_should_close(true)
+            match event {
+                glfw::WindowEvent::Key(Key::Escape, _, Action::Press, _) => window.set_should_close(true),
+                glfw::WindowEvent::Key(Key::F5, _, Action::Press, _) => {
+                    game.save_snapshot("snapshot.txt");
+                }
+                glfw::WindowEvent::Key(Key::F9, _, Action::Press, _) => {
+                    game.load_snapshot("snapshot.txt");
+                }
+                glfw::WindowEvent::Key(Key::N, _, Action::Press, _) => {
+                    game.next_stage();
+                }
+                _ => {}
             }
         }
 
@@ -3,7 +3,6 @@ use crate::collider2d::Collider2d;
 use ::ecs::*;
 use std::cell::RefCell;
 use std::collections::HashSet;
-use std::ops::Deref;
 
 const NODE_SIZE: f32 = 128.;
 
@@ -68,7 +67,7 @@ impl Map {
         (0_f32, 0_f32)
     }
 
-    pub fn try_place(&self, entity: Entity, bounds: Bounds) -> bool {
+    pub fn try_place(&self, ecs: &Ecs, entity: Entity, bounds: Bounds) -> bool {
         let (x, y) = bounds.get_position();
 
         let (ix, iy) = (
@@ -90,7 +89,7 @@ impl Map {
                             let mut objects = checked_cell.objects.borrow_mut();
 
                             for ent in objects.iter() {
-                                if let Some(collider) = ent.get_component_clone::<Collider2d>() {
+                                if let Some(collider) = ecs.get::<Collider2d>(*ent) {
                                     let b = collider.get_bounds();
                                     if bounds.has_collision(&b) {
                                         return false;
@@ -115,12 +114,9 @@ impl Map {
 
     pub fn update(&mut self, world: &EcsRc) {
         let ecs = world.borrow();
-        if let Some(colliders) = ecs.get_container::<Collider2d>() {
-            let colliders = colliders.deref().borrow();
-            for collider in colliders.iter().filter_map(|x| x.as_ref()) {
-                //println!("place b {:?}", bounds);
-                //self.try_place(collider.entity.clone(), collider.get_bounds());
-            }
+        for collider in ecs.query::<&Collider2d>().iter_mut() {
+            //println!("place b {:?}", bounds);
+            //self.try_place(collider.entity.clone(), collider.get_bounds());
         }
         /*
         ecs.visit_all(|collider: &mut Option<Collider2d>| {
@@ -1,35 +1,34 @@
 use ::ecs::*;
-use ecs_derive::component_impl;
 use glfw::ffi::GLFWcharfun;
 
 use crate::{collider2d::CollisionEvent, player_config::PlayerAction, transform::Transform};
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct Movable {
     speed: f32,
-    dirty: bool,
 }
 
 impl Movable {
-    pub fn new(entity: &EntityWeak, speed: f32) -> Self {
+    pub fn new(speed: f32) -> Self {
         Self {
-            entity: entity.clone(),
             speed,
-            dirty: true,
         }
     }
 
     pub fn get_speed(&self) -> f32 {
         self.speed
     }
+}
+
+impl Serializable for Movable {
+    const NAME: &'static str = "movable";
 
-    pub fn set_dirty(&mut self, dirty: bool) {
-        self.dirty = dirty;
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.speed);
     }
 
-    pub fn is_dirty(&self) -> bool {
-        self.dirty
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self::new(reader.read_f32()?))
     }
 }
 
@@ -39,38 +38,44 @@ pub struct BulletSpawner {
     damage: u32,
     pub pos: glm::Vec2,
     pub dir: glm::Vec2,
+    pub prefab: String,
 }
 
 impl BulletSpawner {
-    fn new(owner_id: EntityId, damage: u32, pos: glm::Vec2, dir: glm::Vec2) -> Self {
-        Self { owner_id, damage, pos, dir }
+    fn new(owner_id: EntityId, damage: u32, pos: glm::Vec2, dir: glm::Vec2, prefab: String) -> Self {
+        Self { owner_id, damage, pos, dir, prefab }
     }
 
-    pub fn spawn_bullet(&self, ent: &EntityWeak) -> Bullet {
-        Bullet::new(ent, self.owner_id, self.damage)
+    pub fn spawn_bullet(&self) -> Bullet {
+        Bullet::new(self.owner_id, self.damage)
     }
 }
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct Gun {
     damage: u32,
     timer: f32,
     shoot_delay: f32,
-    spawner: Option<BulletSpawner>
+    triggered: bool,
+    bullet: String,
 }
 
 impl Gun {
-    pub fn new(entity: &EntityWeak, damage: u32) -> Self {
+    pub fn new(damage: u32) -> Self {
         Self {
-            entity: entity.clone(),
             damage,
             timer: 0_f32,
             shoot_delay: 0.3_f32,
-            spawner: None
+            triggered: false,
+            bullet: String::from("bullet"),
         }
     }
 
+    pub fn with_bullet(mut self, bullet: &str) -> Self {
+        self.bullet = String::from(bullet);
+        self
+    }
+
     pub fn get_damage(&self) -> u32 {
         self.damage
     }
@@ -85,32 +90,59 @@ impl Gun {
         }
     }
 
-    pub fn consume_spawner(&mut self) -> Option<BulletSpawner> {
-        if self.spawner.is_some() {
-            self.timer = 0.;
+    pub fn consume_spawner(&mut self, owner_id: EntityId, transform: &Transform) -> Option<BulletSpawner> {
+        if !self.triggered {
+            return None;
         }
-        self.spawner.take()
+
+        self.triggered = false;
+        self.timer = 0.;
+        Some(BulletSpawner::new(
+            owner_id,
+            self.damage,
+            transform.get_position(),
+            transform.get_direction(),
+            self.bullet.clone(),
+        ))
     } 
 }
 
 impl Listener<PlayerAction> for Gun {
-    fn on_event(&mut self, event: PlayerAction) {
+    fn on_event(&mut self, event: &PlayerAction) {
         println!("Gun action event receive");
-        if event == PlayerAction::Shoot && self.can_spawn_bullet() {
-            let id = self.get_entity_id().unwrap();
-            let ent = self.entity.upgrade().unwrap();
-            let tr = ent.get_component_clone::<Transform>().unwrap();
-            self.spawner = Some(BulletSpawner::new(id, self.damage, tr.get_position(), tr.get_direction()))
+        if *event == PlayerAction::Shoot && self.can_spawn_bullet() {
+            self.triggered = true;
         }
     }
 }
 
+impl Serializable for Gun {
+    const NAME: &'static str = "gun";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_u32(self.damage);
+        writer.write_f32(self.timer);
+        writer.write_f32(self.shoot_delay);
+        writer.write_bool(self.triggered);
+        writer.write_str(&self.bullet);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self {
+            damage: reader.read_u32()?,
+            timer: reader.read_f32()?,
+            shoot_delay: reader.read_f32()?,
+            triggered: reader.read_bool()?,
+            bullet: reader.read_str()?,
+        })
+    }
+}
+
 pub enum BulletEvent {
     OnHit(u32)
 }
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct Bullet {
     damage: u32,
     owner: EntityId,
@@ -118,9 +150,8 @@ pub struct Bullet {
 }
 
 impl Bullet {
-    pub fn new(entity: &EntityWeak, owner: EntityId, damage: u32) -> Self {
+    pub fn new(owner: EntityId, damage: u32) -> Self {
         Self {
-            entity: entity.clone(),
             damage: damage.into(),
             owner,
             target: None
@@ -141,38 +172,56 @@ impl Bullet {
 }
 
 impl Listener<CollisionEvent> for Bullet {
-    fn on_event(&mut self, event: CollisionEvent) {
+    fn on_event(&mut self, event: &CollisionEvent) {
         use CollisionEvent::OnEntity;
-        if let OnEntity(ent_id) = event {
+        if let OnEntity(ent_id) = *event {
             //println!("Booomm**** {:?}", ent_id);
             self.target = ent_id.into();
         }
     }
 }
 
-#[component_impl]
-#[derive(Debug, Clone)]
+impl Serializable for Bullet {
+    const NAME: &'static str = "bullet";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_u32(self.damage);
+        writer.write_entity(self.owner);
+    }
+
+    // A pending hit target is transient and dropped on save.
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        let damage = reader.read_u32()?;
+        let owner = reader.read_entity()?;
+
+        Some(Self::new(owner, damage))
+    }
+}
+
+impl MapEntities for Bullet {
+    fn map_entities(&mut self, map: &EntityMap) {
+        self.owner = map.remap(self.owner);
+        self.target = self.target.map(|target| map.remap(target));
+    }
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct Damagable {
     health: u32,
 }
 
 impl Damagable {
-    pub fn new(entity: &EntityWeak, health: u32) -> Self {
+    pub fn new(health: u32) -> Self {
         Self {
-            entity: entity.clone(),
             health,
         }
     }
 
     pub fn do_damage(&mut self, damage: u32) {
-        if let Some(entity) = self.entity.upgrade() {
-            if self.health > damage {
-                self.health -= damage;
-            } else {
-                self.health = 0;
-
-                entity.kill();
-            }
+        if self.health > damage {
+            self.health -= damage;
+        } else {
+            self.health = 0;
         }
         println!("do damage for {}, dmg: {}", self.health, damage);
     }
@@ -186,8 +235,19 @@ impl Damagable {
     }
 }
 
-#[component_impl]
-#[derive(Debug, Clone)]
+impl Serializable for Damagable {
+    const NAME: &'static str = "damagable";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_u32(self.health);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self::new(reader.read_u32()?))
+    }
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct Lifetime {
     timer: f32,
     life_time: f32,
@@ -195,9 +255,8 @@ pub struct Lifetime {
 }
 
 impl Lifetime {
-    pub fn new(entity: &EntityWeak, life_time: f32) -> Self {
+    pub fn new(life_time: f32) -> Self {
         Self {
-            entity: entity.clone(),
             timer: 0.,
             life_time,
             time_out: false,
@@ -213,10 +272,26 @@ impl Lifetime {
 
     pub fn is_time_out(&self) -> bool {
         if self.time_out {
-            if let Some(ent) = self.entity.upgrade() {
-                println!("Lifetime timeout {}, {:?}", self.timer, ent.get_id());
-            }
+            println!("Lifetime timeout {}", self.timer);
         }
         self.time_out
     }
 }
+
+impl Serializable for Lifetime {
+    const NAME: &'static str = "lifetime";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.timer);
+        writer.write_f32(self.life_time);
+        writer.write_bool(self.time_out);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self {
+            timer: reader.read_f32()?,
+            life_time: reader.read_f32()?,
+            time_out: reader.read_bool()?,
+        })
+    }
+}
@@ -1,20 +1,13 @@
-use std::cell::RefMut;
 use std::{collections::HashMap, fs};
 use fxhash::FxHashMap;
-use ecs::EntityWeak;
 use glfw::{Key, Action};
 use yaml_rust2::{Yaml, YamlLoader};
 use std::str::FromStr;
 use strum_macros::EnumString;
 use ::ecs::*;
-use ecs_derive::component_impl;
-use crate::bounds::Bounds;
-use crate::collider2d::Collider2d;
-use crate::quad_tree::QuadTree;
-use crate::object_components::{Bullet, Damagable, Gun, Lifetime, Movable};
+use crate::prefab::{PrefabRegistry, SpawnParams};
 use crate::render::Render;
 use crate::sprite::Sprite;
-use crate::transform::Transform;
 use crate::game::InputLayoutComponent;
 
 
@@ -24,8 +17,7 @@ pub enum PlayerState {
     Move
 }
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct PlayerController {
     pub direction: glm::Vec2,
     pub player_index: u32,
@@ -33,8 +25,8 @@ pub struct PlayerController {
 }
 
 impl Listener<PlayerAction> for PlayerController {
-    fn on_event(&mut self, player_input: PlayerAction) {
-        self.direction = match player_input {
+    fn on_event(&mut self, player_input: &PlayerAction) {
+        self.direction = match *player_input {
             PlayerAction::MoveLeft => glm::vec2(-1., 0.),
             PlayerAction::MoveRight => glm::vec2(1., 0.),
             PlayerAction::MoveTop => glm::vec2(0., 1.),
@@ -42,7 +34,7 @@ impl Listener<PlayerAction> for PlayerController {
             PlayerAction::None | PlayerAction::Shoot => self.direction
         };
 
-        self.state = match player_input {
+        self.state = match *player_input {
             PlayerAction::MoveLeft |
             PlayerAction::MoveRight |
             PlayerAction::MoveTop |
@@ -56,9 +48,8 @@ impl Listener<PlayerAction> for PlayerController {
 }
 
 impl PlayerController {
-    fn new(entity: &EntityWeak, player_index: u32, dir: glm::Vec2) -> Self {
+    fn new(player_index: u32, dir: glm::Vec2) -> Self {
         Self {
-            entity: entity.clone(),
             direction: glm::vec2(dir.x, dir.y),
             player_index,
             state: PlayerState::Idle,
@@ -66,6 +57,24 @@ impl PlayerController {
     }
 }
 
+impl Serializable for PlayerController {
+    const NAME: &'static str = "player_controller";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.direction.x);
+        writer.write_f32(self.direction.y);
+        writer.write_u32(self.player_index);
+        writer.write_bool(self.state == PlayerState::Move);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        let direction = glm::vec2(reader.read_f32()?, reader.read_f32()?);
+        let player_index = reader.read_u32()?;
+        let state = if reader.read_bool()? { PlayerState::Move } else { PlayerState::Idle };
+
+        Some(Self { direction, player_index, state })
+    }
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, EnumString)]
 pub enum PlayerAction {
@@ -145,50 +154,51 @@ impl PlayerConfig {
         None
     }
 
-    fn get_input_component(&self, entity: EntityWeak) -> InputLayoutComponent {
-        InputLayoutComponent::new(entity, self.key_actions.clone())
+    fn get_input_component(&self) -> InputLayoutComponent {
+        InputLayoutComponent::new(self.key_actions.clone())
     }
 }
 
 pub struct Player {
-    entity: EntityWeak,
+    entity: Entity,
     //pub action: PlayerAction,
     config: PlayerConfig,
 }
 
 impl Player {
-   pub fn new(ecs: &EcsRc, index: u32, config: &str, quad_tree: RefMut<'_, QuadTree>, render: &Render) -> Option<Self> {
+   pub fn new(ecs: &EcsRc, index: u32, config: &str, render: &Render) -> Option<Self> {
         let config = PlayerConfig::new(config).unwrap();
-        let entity_weak = Entity::new(ecs);
-        let entity = entity_weak.upgrade()?;
         let dir = glm::vec2(0_f32, 1_f32);
         let pos = glm::vec2(50. + (900 * index) as f32, 100.);
-        let size = 50_f32;
 
-        let bounds = Bounds::with_center_position(pos.x, pos.y, size, size);
-
-        entity.add_component(|| Sprite::new(&entity_weak, size, size, "tank.png"));
-        entity.add_component(|| Transform::with_direction(&entity_weak, pos, dir));
-        entity.add_component(|| Collider2d::new(&entity_weak, bounds));
-        entity.add_component(|| Movable::new(&entity_weak, 200.));
-        entity.add_component(|| Damagable::new(&entity_weak, 10));
-        entity.add_component(|| PlayerController::new(&entity_weak, index, dir));
-        entity.add_component(|| Gun::new(&entity_weak, 2));
-
-        entity.visit(|sprite: &mut Option<Sprite>| sprite.as_mut().unwrap().init(render));
-        
-        let input = config.get_input_component(entity_weak.clone());
-        entity.add_component(|| input);
-        quad_tree.place( ecs.borrow(), &entity);
+        let tank = ecs
+            .borrow()
+            .resource::<PrefabRegistry>()?
+            .instantiate("player", &SpawnParams::new(pos, dir))?;
+        let entity = ecs.borrow_mut().spawn((
+            tank,
+            PlayerController::new(index, dir),
+            config.get_input_component(),
+            Name::new(format!("player {}", index + 1)),
+        ));
+
+        let world = ecs.borrow();
+        world.get_mut::<Sprite>(entity)?.init(render);
         Self {
-            entity: entity_weak.clone(),
+            entity,
             //action: PlayerAction::None,
             config,
         }.into()
     }
 
-    fn get_player_entity(&self) -> EntityWeak {
-        self.entity.clone()
+    pub fn get_player_entity(&self) -> Entity {
+        self.entity
+    }
+
+    // Key bindings are not part of a snapshot, they come from the player config.
+    pub fn attach(&mut self, ecs: &mut Ecs, entity_id: EntityId) {
+        ecs.insert_component(entity_id, self.config.get_input_component());
+        self.entity = entity_id;
     }
 
     // fn do_input(&mut self, event: &glfw::WindowEvent) {
@@ -0,0 +1,219 @@
+use std::fs;
+
+use ::ecs::*;
+use fxhash::FxHashMap;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::bounds::Bounds;
+use crate::collider2d::Collider2d;
+use crate::object_components::{Damagable, Gun, Lifetime, Movable};
+use crate::sprite::Sprite;
+use crate::transform::{GlobalTransform, Transform};
+
+pub struct SpawnParams {
+    pub pos: glm::Vec2,
+    pub dir: glm::Vec2,
+    pub owner: Option<EntityId>,
+}
+
+impl SpawnParams {
+    pub fn new(pos: glm::Vec2, dir: glm::Vec2) -> Self {
+        Self { pos, dir, owner: None }
+    }
+
+    pub fn with_owner(pos: glm::Vec2, dir: glm::Vec2, owner: EntityId) -> Self {
+        Self { pos, dir, owner: Some(owner) }
+    }
+}
+
+// Components built from a prefab, spawned like any other bundle.
+pub type PrefabBundle = DynamicBundle;
+
+pub type ComponentLoader = fn(&Yaml, &SpawnParams, &mut PrefabBundle) -> Option<()>;
+
+pub fn get_f32(data: &Yaml, key: &str) -> Option<f32> {
+    let value = &data[key];
+    value
+        .as_f64()
+        .or_else(|| value.as_i64().map(|v| v as f64))
+        .map(|v| v as f32)
+}
+
+fn get_bounds(data: &Yaml, pos: glm::Vec2) -> Option<Bounds> {
+    let width = get_f32(data, "width")?;
+    let height = get_f32(data, "height")?;
+
+    Some(Bounds::with_center_position(pos.x, pos.y, width, height))
+}
+
+fn parse_sprite(data: &Yaml) -> Option<Sprite> {
+    let texture = data["texture"].as_str()?;
+    Some(Sprite::new(get_f32(data, "width")?, get_f32(data, "height")?, texture))
+}
+
+fn load_sprite(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    bundle.add(parse_sprite(data)?);
+    Some(())
+}
+
+fn load_collider(data: &Yaml, params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    let bounds = get_bounds(data, params.pos)?;
+    match params.owner {
+        Some(owner) => bundle.add(Collider2d::with_ignore(bounds, vec![owner])),
+        None => bundle.add(Collider2d::new(bounds)),
+    }
+    Some(())
+}
+
+fn load_movable(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    bundle.add(Movable::new(get_f32(data, "speed")?));
+    Some(())
+}
+
+fn load_damagable(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    bundle.add(Damagable::new(data["health"].as_i64()? as u32));
+    Some(())
+}
+
+fn load_gun(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    let mut gun = Gun::new(data["damage"].as_i64()? as u32);
+    if let Some(bullet) = data["bullet"].as_str() {
+        gun = gun.with_bullet(bullet);
+    }
+    bundle.add(gun);
+    Some(())
+}
+
+fn load_lifetime(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    bundle.add(Lifetime::new(get_f32(data, "time")?));
+    Some(())
+}
+
+fn load_name(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    bundle.add(Name::new(data.as_str()?));
+    Some(())
+}
+
+fn load_tags(data: &Yaml, _params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    let tags = data.as_vec()?.iter().filter_map(|tag| tag.as_str());
+    bundle.add(Tags::new(tags));
+    Some(())
+}
+
+pub struct PrefabRegistry {
+    loaders: FxHashMap<String, ComponentLoader>,
+    prefabs: FxHashMap<String, Yaml>,
+}
+
+impl PrefabRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self {
+            loaders: FxHashMap::default(),
+            prefabs: FxHashMap::default(),
+        };
+
+        registry.register_loader("sprite", load_sprite);
+        registry.register_loader("collider", load_collider);
+        registry.register_loader("movable", load_movable);
+        registry.register_loader("damagable", load_damagable);
+        registry.register_loader("gun", load_gun);
+        registry.register_loader("lifetime", load_lifetime);
+        registry.register_loader("name", load_name);
+        registry.register_loader("tags", load_tags);
+
+        registry
+    }
+
+    pub fn register_loader(&mut self, name: &str, loader: ComponentLoader) {
+        self.loaders.insert(name.to_string(), loader);
+    }
+
+    pub fn load(&mut self, path: &str) -> bool {
+        let content = match fs::read_to_string(std::path::Path::new(path)) {
+            Ok(content) => content,
+            Err(err) => {
+                println!("Failed to read prefabs {}: {}", path, err);
+                return false;
+            }
+        };
+
+        let docs = match YamlLoader::load_from_str(&content) {
+            Ok(docs) => docs,
+            Err(err) => {
+                println!("Failed to parse prefabs {}: {}", path, err);
+                return false;
+            }
+        };
+
+        if let Some(prefabs) = docs.first().and_then(|doc| doc["prefabs"].as_hash()) {
+            for (name, prefab) in prefabs.iter() {
+                if let Some(name) = name.as_str() {
+                    self.prefabs.insert(name.to_string(), prefab.clone());
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.prefabs.contains_key(name)
+    }
+
+    pub fn get_bounds(&self, name: &str, pos: glm::Vec2) -> Option<Bounds> {
+        get_bounds(&self.prefabs.get(name)?["components"]["collider"], pos)
+    }
+
+    // Sprite of the prefab without a draw instance, to compare with sprites
+    // of pooled entities.
+    pub fn get_sprite(&self, name: &str) -> Option<Sprite> {
+        parse_sprite(&self.prefabs.get(name)?["components"]["sprite"])
+    }
+
+    pub fn instantiate(&self, name: &str, params: &SpawnParams) -> Option<PrefabBundle> {
+        self.instantiate_without(name, params, &[])
+    }
+
+    // Leaves out the `skip` components, e.g. the ones a reused entity keeps.
+    pub fn instantiate_without(&self, name: &str, params: &SpawnParams, skip: &[&str]) -> Option<PrefabBundle> {
+        let prefab = match self.prefabs.get(name) {
+            Some(prefab) => prefab,
+            None => {
+                println!("Unknown prefab {}", name);
+                return None;
+            }
+        };
+
+        let mut bundle = PrefabBundle::default();
+        bundle.add((Transform::with_direction(params.pos, params.dir), GlobalTransform::new()));
+
+        if let Some(components) = prefab["components"].as_hash() {
+            for (component, data) in components.iter() {
+                let component = component.as_str().unwrap_or_default();
+                if skip.contains(&component) {
+                    continue;
+                }
+                match self.loaders.get(component) {
+                    Some(loader) => {
+                        if loader(data, params, &mut bundle).is_none() {
+                            println!("Prefab {} has invalid {} component", name, component);
+                        }
+                    }
+                    None => println!("Prefab {} has unknown component {}", name, component),
+                }
+            }
+        }
+
+        Some(bundle)
+    }
+}
+
+pub fn spawn_prefab(world: &EcsRc, name: &str, pos: glm::Vec2) -> Option<Entity> {
+    let params = SpawnParams::new(pos, glm::vec2(0., 1.));
+    let bundle = world
+        .borrow()
+        .resource::<PrefabRegistry>()?
+        .instantiate(name, &params)?;
+
+    Some(world.borrow_mut().spawn(bundle))
+}
@@ -1,8 +1,8 @@
 use crate::bounds::Bounds;
 use crate::collider2d::Collider2d;
 use ecs::*;
-use std::cell::{Cell, Ref, RefCell};
 use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
 
 const CHUNKS: usize = 4;
 const MAX_DEPTH: u32 = 3;
@@ -43,7 +43,7 @@ struct AreaNode {
     parent: Handle,
     bounds: Bounds,
     children: Option<[Handle; CHUNKS]>,
-    objects: RefCell<HashSet<EntityId>>,
+    objects: Mutex<HashSet<EntityId>>,
 }
 
 impl AreaNode {
@@ -62,14 +62,14 @@ impl AreaNode {
     }
 
     fn insert_object(&self, object_id: EntityId) {
-        let mut objects = self.objects.borrow_mut();
+        let mut objects = self.objects.lock().unwrap();
 
         println!("Object {:?} enter area {:?}", object_id, self.handle.index);
         objects.insert(object_id);
     }
 
     fn remove_object(&self, object_id: EntityId) {
-        let mut objects = self.objects.borrow_mut();
+        let mut objects = self.objects.lock().unwrap();
         println!("Object {:?} leave area {:?}", object_id, self.handle.index);
         objects.remove(&object_id);
     }
@@ -82,7 +82,7 @@ pub struct CollisionSummary {
 
 pub struct QuadTree {
     nodes: [Option<AreaNode>; MAX_NODE_COUNT],
-    tail: Cell<usize>,
+    tail: usize,
     bounds: Bounds,
     root: Option<Handle>,
 }
@@ -92,19 +92,20 @@ impl QuadTree {
     pub fn new(bounds: Bounds) -> Self {
         Self {
             nodes: std::array::from_fn(|_| None),
-            tail: Cell::new(0),
+            tail: 0,
             bounds,
             root: None,
         }
         .build_tree()
     }
 
-    fn allocate(&self) -> Handle {
-        Handle::new(self.tail.replace(self.tail.get() + 1))
+    fn allocate(&mut self) -> Handle {
+        self.tail += 1;
+        Handle::new(self.tail - 1)
     }
 
     fn insert(&mut self, handle: Handle, node: AreaNode) {
-        assert!(handle.index < self.tail.get());
+        assert!(handle.index < self.tail);
         self.nodes[handle.index] = Some(node);
     }
 
@@ -146,7 +147,7 @@ impl QuadTree {
             parent,
             bounds,
             children,
-            objects: RefCell::new(HashSet::new()),
+            objects: Mutex::new(HashSet::new()),
         };
         self.insert(handle, node);
 
@@ -202,37 +203,29 @@ impl QuadTree {
     }
 
     pub fn on_entity_removed(&self, id: EntityId) {
-        let handle = self.find_child_node_if(self.root.unwrap(), |node| {
-            if let Some(node) = node {
-                let objects = node.objects.borrow();
-
-                return objects.contains(&id);
-            }
-            false
-        });
-        if let Some(handle) = handle {
-            if let Some(node) = &self.nodes[handle.index] {
+        for node in self.nodes.iter().flatten() {
+            let contains = node.objects.lock().unwrap().contains(&id);
+            if contains {
                 node.remove_object(id);
             }
         }
     }
 
-    pub fn can_place(&self, ecs: Ref<'_, Ecs>, bounds: &Bounds) -> bool {
+    pub fn can_place(&self, ecs: &Ecs, bounds: &Bounds) -> bool {
         
         let ignored = None;
         
-        if let Some(id) = self.get_collision(&ecs,None, &ignored, &bounds) {
+        if let Some(id) = self.get_collision(ecs, None, &ignored, bounds) {
             return false;
         }
         true
     }
 
-    pub fn place(&self, ecs: Ref<'_, Ecs>, entity: &Entity) -> bool {
-        let collider = entity.get_component_clone::<Collider2d>();
-        if let Some(collider) = collider {
-            let bounds = collider.get_bounds();
+    pub fn place(&self, ecs: &Ecs, entity: Entity) -> bool {
+        let bounds = ecs.get::<Collider2d>(entity).map(|collider| collider.get_bounds());
+        if let Some(bounds) = bounds {
             let ignored = None;
-            if let Some(id) = self.get_collision(&ecs, entity.get_id().into(), &ignored, &bounds) {
+            if let Some(id) = self.get_collision(ecs, entity.into(), &ignored, &bounds) {
                 return false;
             }
 
@@ -241,8 +234,8 @@ impl QuadTree {
             if let Some(handle) = handle {
                 let node = self.nodes[handle.index].as_ref().unwrap();
 
-                node.insert_object(entity.get_id());
-                entity.visit::<Collider2d>(|collider| {
+                node.insert_object(entity);
+                ecs.visit::<Collider2d>(entity, |collider| {
                     if let Some(collider) = collider {
                         collider.set_area_handle(Some(handle));
                     }
@@ -256,7 +249,7 @@ impl QuadTree {
 
     fn get_collision(
         &self,
-        ecs: &Ref<'_, Ecs>,
+        ecs: &Ecs,
         entity_id: Option<EntityId>,
         ignored: &Option<Box<[EntityId]>>,
         bounds: &Bounds,
@@ -268,19 +261,18 @@ impl QuadTree {
         while let Some(handle) = next_handle {
             if handle.index < nodes_count {
                 let node = self.nodes[handle.index].as_ref().unwrap();
-                for c in node.objects.borrow().iter().filter_map(|e| {
+                for e in node.objects.lock().unwrap().iter() {
                     let ignore = if let Some(ignored) = ignored {
                         ignored.contains(e)
                     } else { false };
-                    if entity_id.filter(|id| *id == *e).is_none() && !ignore {
-                        ecs.get_component::<Collider2d>(*e)
-                    } else {
-                        None
+                    if entity_id.filter(|id| *id == *e).is_some() || ignore {
+                        continue;
                     }
-                }) {
-                    if c.get_bounds().has_collision(bounds) {
-
-                        return c.get_entity_id();
+                    let collide = ecs
+                        .get::<Collider2d>(*e)
+                        .is_some_and(|c| c.get_bounds().has_collision(bounds));
+                    if collide {
+                        return Some(*e);
                     }
                 }
                 if let Some(node_children) = node.children {
@@ -301,33 +293,36 @@ impl QuadTree {
         None
     }
 
-    pub fn move_object(&self, ecs: &Ref<'_, Ecs>, entity: &Entity, new_pos: glm::Vec2) -> CollisionSummary {
-        if let Some(collider) = ecs.get_component::<Collider2d>(entity.get_id()) {
-            let mut bounds = collider.get_bounds();
+    pub fn move_object(&self, ecs: &Ecs, entity_id: EntityId, new_pos: glm::Vec2) -> CollisionSummary {
+        // The collider is written below, so only copies outlive this borrow.
+        let collider = ecs.get::<Collider2d>(entity_id).map(|collider| {
+            (collider.get_bounds(), collider.get_area_handle(), collider.collision_ignore.clone())
+        });
+        if let Some((mut bounds, area_handle, collision_ignore)) = collider {
             bounds.set_center_position(new_pos.x, new_pos.y);
             let handle = self.get_place_node(&bounds);
 
             if let Some(handle) = handle {
                 let collide_ent = self.get_collision(
                     ecs, 
-                    entity.get_id().into(), 
-                    &collider.collision_ignore, 
+                    entity_id.into(), 
+                    &collision_ignore, 
                     &bounds);
 
                 if collide_ent.is_some() {
                     return CollisionSummary{ collide_ent, can_move: false };
                 }
-                if let Some(old_handle) = collider.get_area_handle() {
+                if let Some(old_handle) = area_handle {
                     if old_handle != handle {
                         let node = self.nodes[handle.index].as_ref().unwrap();
-                        node.insert_object(entity.get_id());
+                        node.insert_object(entity_id);
                         if let Some(node) = &self.nodes[old_handle.index] {
-                            node.remove_object(entity.get_id());
+                            node.remove_object(entity_id);
                         }
                     }
                 }
 
-                ecs.visit::<Collider2d>(entity.get_id(), |collider| {
+                ecs.visit::<Collider2d>(entity_id, |collider| {
                     if let Some(collider) = collider.as_mut() {
                         collider.set_position(new_pos.x, new_pos.y);
                         collider.set_reached_border(false);
@@ -338,7 +333,7 @@ impl QuadTree {
                 return CollisionSummary{ collide_ent: None, can_move: true };
             }
         }
-        ecs.visit::<Collider2d>(entity.get_id(), |collider| {
+        ecs.visit::<Collider2d>(entity_id, |collider| {
             if let Some(collider) = collider.as_mut() {
                 collider.set_reached_border(true);
             }
@@ -4,7 +4,7 @@ use std::ffi::{c_void, CStr, CString};
 use crate::gl_wrappers::{self, Bindable, VertexArrayObject};
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::sync::Arc;
 use std::{mem, ptr};
 
 use image::io::Reader as ImageReader;
@@ -180,7 +180,7 @@ pub fn draw_instance(program: ShaderProgram, vao: u32, texture: u32) {
 }
 
 pub struct ShaderCache {
-    programs: HashMap<String, Rc<ShaderProgram>>,
+    programs: HashMap<String, Arc<ShaderProgram>>,
 }
 
 impl ShaderCache {
@@ -194,18 +194,18 @@ impl ShaderCache {
         self.programs.contains_key(name)
     }
 
-    pub fn get_program(&mut self, name: &str) -> Option<Rc<ShaderProgram>> {
+    pub fn get_program(&mut self, name: &str) -> Option<Arc<ShaderProgram>> {
         match self.programs.get(name) {
             Some(value) => Some(value.clone()),
             None => self.try_load_program(name),
         }
     }
 
-    fn try_load_program(&mut self, name: &str) -> Option<Rc<ShaderProgram>> {
+    fn try_load_program(&mut self, name: &str) -> Option<Arc<ShaderProgram>> {
         if name == "default" {
             if let Some(default_program) = make_default_shader_program() {
-                let program = Rc::new(default_program);
-                self.programs.insert(name.to_string(), Rc::clone(&program));
+                let program = Arc::new(default_program);
+                self.programs.insert(name.to_string(), Arc::clone(&program));
 
                 return Some(program);
             }
@@ -234,7 +234,7 @@ impl Render {
         }
     }
 
-    pub fn get_shader(&self, name: &str) -> Option<Rc<ShaderProgram>> {
+    pub fn get_shader(&self, name: &str) -> Option<Arc<ShaderProgram>> {
         let mut shader_cache = self.shader_cache.borrow_mut();
         if shader_cache.is_program_exist(name) {
             shader_cache.get_program(name)
@@ -0,0 +1,52 @@
+use ecs::EntityId;
+use fxhash::FxHashMap;
+
+#[derive(Debug, Default)]
+pub struct GameClock {
+    delta: f32,
+    elapsed: f32,
+    frame: u32,
+}
+
+impl GameClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tick(&mut self, delta: f32) {
+        self.delta = delta;
+        self.elapsed += delta;
+        self.frame += 1;
+    }
+
+    pub fn get_delta(&self) -> f32 {
+        self.delta
+    }
+
+    pub fn get_elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn get_frame(&self) -> u32 {
+        self.frame
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Score {
+    kills: FxHashMap<EntityId, u32>,
+}
+
+impl Score {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_kill(&mut self, killer: EntityId) {
+        *self.kills.entry(killer).or_insert(0) += 1;
+    }
+
+    pub fn get_kills(&self, id: EntityId) -> u32 {
+        self.kills.get(&id).copied().unwrap_or(0)
+    }
+}
@@ -1,15 +1,13 @@
 use crate::draw_instance::DrawInstance;
 use crate::render::{make_quad, Drawable, Render};
 use ::ecs::*;
-use ecs_derive::component_impl;
 use std::convert::From;
-use std::rc::Rc;
+use std::sync::Arc;
 extern crate nalgebra_glm as glm;
 
-#[component_impl]
-#[derive(Debug, Clone)]
+#[derive(Component, Debug, Clone)]
 pub struct Sprite {
-    instance: Option<Rc<DrawInstance>>,
+    instance: Option<Arc<DrawInstance>>,
     program_name: String,
     texture_name: String,
     width: f32,
@@ -17,9 +15,8 @@ pub struct Sprite {
 }
 
 impl Sprite {
-    pub fn new(entity: &EntityWeak, width: f32, height: f32, texture_name: &str) -> Self {
+    pub fn new(width: f32, height: f32, texture_name: &str) -> Self {
         Sprite {
-            entity: entity.clone(),
             instance: None,
             program_name: String::from("default"),
             texture_name: String::from(texture_name),
@@ -36,7 +33,7 @@ impl Sprite {
         let texture = render.load_texture(self.texture_name.as_str());
         let instance = DrawInstance::new(program.unwrap(), vao, texture);
 
-        self.instance = Some(Rc::new(instance));
+        self.instance = Some(Arc::new(instance));
     }
 
     pub fn get_width(&self) -> f32 {
@@ -50,6 +47,41 @@ impl Sprite {
     pub fn is_initizlized(&self) -> bool {
         self.instance.is_some()
     }
+
+    // Same quad and texture, so one draw instance fits both.
+    pub fn same_look(&self, other: &Sprite) -> bool {
+        self.program_name == other.program_name
+            && self.texture_name == other.texture_name
+            && self.width == other.width
+            && self.height == other.height
+    }
+}
+
+// The draw instance is recreated on the first draw after loading.
+impl Serializable for Sprite {
+    const NAME: &'static str = "sprite";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.width);
+        writer.write_f32(self.height);
+        writer.write_str(&self.program_name);
+        writer.write_str(&self.texture_name);
+    }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        let width = reader.read_f32()?;
+        let height = reader.read_f32()?;
+        let program_name = reader.read_str()?;
+        let texture_name = reader.read_str()?;
+
+        Some(Self {
+            instance: None,
+            program_name,
+            texture_name,
+            width,
+            height,
+        })
+    }
 }
 
 impl Drawable for Sprite {
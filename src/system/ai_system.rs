@@ -1,32 +1,47 @@
-use std::{cell::RefCell, rc::Rc};
-
-use ecs::{Component, EcsRc, Entity, EntityId, EntityWeak};
-use ecs_derive::component_impl;
+use ecs::{Access, Component, EcsRc, Serializable, SnapshotReader, SnapshotWriter, System};
+use yaml_rust2::Yaml;
 
 use crate::{
-    bounds::Bounds, collider2d::Collider2d, object_components::{Bullet, BulletSpawner, Damagable, Gun, Lifetime, Movable}, quad_tree::{self, QuadTree}, sprite::Sprite, transform::Transform
+    prefab::{spawn_prefab, PrefabBundle, PrefabRegistry, SpawnParams}, quad_tree::QuadTree, resources::GameClock
 };
-use super::system_trait::System;
 
 enum AiCommand {
     ChangeDir,
     Shoot,
 }
 
-#[component_impl]
-struct AiController {
+#[derive(Component)]
+pub struct AiController {
     dir: glm::Vec2,
 }
 
 impl AiController {
-    fn new(entity: &EntityWeak, dir: glm::Vec2) -> Self {
-        Self { entity: entity.clone(), dir }
+    fn new(dir: glm::Vec2) -> Self {
+        Self { dir }
+    }
+}
+
+impl Serializable for AiController {
+    const NAME: &'static str = "ai_controller";
+
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.dir.x);
+        writer.write_f32(self.dir.y);
     }
+
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self::new(glm::vec2(reader.read_f32()?, reader.read_f32()?)))
+    }
+}
+
+pub fn load_ai_controller(_data: &Yaml, params: &SpawnParams, bundle: &mut PrefabBundle) -> Option<()> {
+    bundle.add(AiController::new(params.dir));
+    Some(())
 }
 
 #[derive(Clone, Copy)]
 enum SpawnItem {
-    Tank,
+    Tank(&'static str),
     Bonus
 }
 
@@ -39,12 +54,12 @@ struct SpawnPoint {
 }
 
 impl SpawnPoint {
-    fn new(pos: glm::Vec2) -> Self {
+    fn new(pos: glm::Vec2, spawn_item: SpawnItem) -> Self {
         SpawnPoint{ 
             pos,
             spawn_delay: 3.0, 
             timer: 0.0, 
-            spawn_item: SpawnItem::Tank,
+            spawn_item,
             spawned: None
         }
     }
@@ -64,48 +79,34 @@ impl SpawnPoint {
 }
 
 pub struct AiSystem {
-    quad_tree: Rc<RefCell<QuadTree>>,
     spawn_points: Vec<SpawnPoint>,
 }
 
 impl AiSystem {
-    pub fn new(quad_tree: Rc<RefCell<QuadTree>>) -> Self {
+    pub fn new() -> Self {
         let spawn_points= vec![ 
-            SpawnPoint::new(glm::vec2(50., 700.)),
-            SpawnPoint::new(glm::vec2(980., 700.)),
+            SpawnPoint::new(glm::vec2(50., 700.), SpawnItem::Tank("enemy")),
+            SpawnPoint::new(glm::vec2(980., 700.), SpawnItem::Tank("enemy_fast")),
         ];
         Self{ 
-            quad_tree,
             spawn_points 
         }
     }
 
-    fn spawn_tank(&mut self, world: &ecs::EcsRc, pos: glm::Vec2) {
+    fn spawn_tank(world: &ecs::EcsRc, prefab: &str, pos: glm::Vec2) {
+        {
+            let ecs = world.borrow();
+            let bounds = ecs.resource::<PrefabRegistry>().unwrap().get_bounds(prefab, pos);
+            let quad_tree = ecs.resource::<QuadTree>().unwrap();
+            if !bounds.is_some_and(|bounds| quad_tree.can_place(&ecs, &bounds)) {
+                println!("AI Tank failed spawn");
+                return;
+            }
+        }
 
-        let dir = glm::vec2(0_f32, 1_f32);
-        let size = 50_f32;
-        let bounds = Bounds::with_center_position(pos.x, pos.y, size, size);
-        let quad_tree = self.quad_tree.borrow_mut();
-        if !quad_tree.can_place(world.borrow(), &bounds) {
-            println!("AI Tank failed spawn");
+        if spawn_prefab(world, prefab, pos).is_none() {
             return;
         }
-        drop(quad_tree);
-
-        let entity_weak = Entity::new(world);
-        let entity = entity_weak.upgrade().unwrap();
-
-        entity.add_component(|| Sprite::new(&entity_weak, size, size, "tank.png"));
-        entity.add_component(|| Transform::with_direction(&entity_weak, pos, dir));
-        entity.add_component(|| Collider2d::new(&entity_weak, bounds));
-        entity.add_component(|| Movable::new(&entity_weak, 200.));
-        entity.add_component(|| Damagable::new(&entity_weak, 10));
-        entity.add_component(|| AiController::new(&entity_weak, dir));
-        entity.add_component(|| Gun::new(&entity_weak, 2));
-
-
-        let quad_tree = self.quad_tree.borrow_mut();
-        quad_tree.place(world.borrow(), &entity);
 
         println!("On AI Tank spawned");
     }
@@ -126,7 +127,12 @@ impl AiSystem {
 
         while let Some(item) = spawn_items.pop() {
             match item.0 {
-                SpawnItem::Tank => self.spawn_tank(world, item.1),
+                SpawnItem::Tank(prefab) => {
+                    // Spawning waits for the sync point, so the system can
+                    // run next to the others.
+                    let pos = item.1;
+                    world.borrow().commands().add(move |world| Self::spawn_tank(world, prefab, pos));
+                }
                 SpawnItem::Bonus => self.spawn_bonus(world),
             }
         }
@@ -134,8 +140,13 @@ impl AiSystem {
 }
 
 impl System for AiSystem {
-    fn update(&mut self, world: &ecs::EcsRc, delta: f32) {
-        
+    fn access(&self) -> Option<Access> {
+        Some(Access::new().resource::<GameClock>())
+    }
+
+    fn update(&mut self, world: &ecs::EcsRc) {
+        let delta = world.borrow().resource::<GameClock>().unwrap().get_delta();
+
         self.spawn_items(world, delta);
     }
 }
\ No newline at end of file
@@ -0,0 +1,43 @@
+use ecs::*;
+
+use crate::{
+    collider2d::Collider2d,
+    object_components::{Bullet, Lifetime, Movable},
+    resources::GameClock,
+};
+
+pub struct BulletSystem {}
+
+impl BulletSystem {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+type BulletQuery<'a> = (&'a mut Lifetime, &'a mut Movable, &'a Collider2d);
+
+impl System for BulletSystem {
+    fn access(&self) -> Option<Access> {
+        Some(
+            Access::new()
+                .query_filtered::<BulletQuery, With<Bullet>>()
+                .resource::<GameClock>(),
+        )
+    }
+
+    fn update(&mut self, world: &EcsRc) {
+        let ecs = world.borrow();
+        let dt = ecs.resource::<GameClock>().unwrap().get_delta();
+        let mut commands = ecs.commands();
+        let mut bullets = ecs.query_filtered::<BulletQuery, With<Bullet>>();
+        for (id, (mut lifetime, mut movable, collider)) in bullets.iter_with_id() {
+            lifetime.update(dt);
+
+            if collider.is_reached_border() || lifetime.is_time_out() {
+                commands.despawn_pooled::<Bullet>(id);
+            } else {
+                movable.set_changed();
+            }
+        }
+    }
+}
@@ -1,83 +1,85 @@
-use std::cell::RefCell;
-
 use crate::{
-    bounds::Bounds, 
-    collider2d::Collider2d, 
-    object_components::{Bullet, BulletSpawner, Damagable, Gun, Lifetime, Movable}, 
-    sprite::Sprite, transform::Transform,
-    system::system_trait::System
+    object_components::{Bullet, Damagable, Gun},
+    prefab::{PrefabRegistry, SpawnParams},
+    resources::{GameClock, Score},
+    sprite::Sprite,
+    transform::Transform,
 };
 use ecs::*;
 
-pub struct FireSystem {
-    spawners: RefCell<Vec<BulletSpawner>>,
-}
+type GunQuery<'a> = (&'a mut Gun, &'a Transform);
+
+pub struct FireSystem {}
 
 impl FireSystem {
     pub fn new() -> Self {
-        Self { spawners: RefCell::new(Vec::new()) }
+        Self {}
     }
 
-    fn update_guns(&self, world: &EcsRc, delta: f32) {
-        let ecs = world.borrow();
-        
-        ecs.visit_all::<Gun>(|gun|{
-            gun.update_timer(delta);
-            let owner = gun.entity.upgrade().unwrap();
-            let tr_owner = owner.get_component_clone::<Transform>().unwrap();
-            let direction = tr_owner.get_direction();
-            let owner_id = owner.get_id();
-            if let Some(spawner) = gun.consume_spawner() {
-                let mut spawners = self.spawners.borrow_mut();
-                spawners.push(spawner);
-            }
+    // A parked bullet with the same sprite keeps it and its draw instance,
+    // only the state from the prefab is written again.
+    fn spawn_bullet(world: &EcsRc, prefab: &str, params: &SpawnParams, bullet: Bullet) {
+        let mut ecs = world.borrow_mut();
+        let sprite = match ecs.resource::<PrefabRegistry>() {
+            Some(prefabs) => prefabs.get_sprite(prefab),
+            None => return,
+        };
+        let reused = ecs.take_pooled::<Bullet>(|ecs, entity| {
+            let parked = ecs.get::<Sprite>(entity);
+            matches!((parked, &sprite), (Some(parked), Some(sprite)) if parked.same_look(sprite))
         });
+
+        let skip: &[&str] = if reused.is_some() { &["sprite"] } else { &[] };
+        let bundle = ecs
+            .resource::<PrefabRegistry>()
+            .and_then(|prefabs| prefabs.instantiate_without(prefab, params, skip));
+        let Some(bundle) = bundle else {
+            return;
+        };
+
+        match reused {
+            Some(entity) => ecs.insert_bundle(entity, (bundle, bullet)),
+            None => {
+                ecs.spawn((bundle, bullet));
+            }
+        }
     }
 
-    fn spawn_bullets(&self, world: &EcsRc) {
-        let mut spawners = self.spawners.borrow_mut();
-        while let Some(spawner) = spawners.pop() {
-            let direction = glm::vec2(0., 0.);//tr_owner.get_direction();
-            let weak_bullet = Entity::new(world);
-            let bullet = weak_bullet.upgrade().unwrap();
-            bullet.add_component(|| spawner.spawn_bullet(&weak_bullet));
+    fn update_guns(&self, world: &EcsRc, delta: f32) {
+        let ecs = world.borrow();
+        let mut commands = ecs.commands();
 
-            let pos = spawner.pos + spawner.dir * 30.;
-            bullet.add_component(|| {
-                Transform::with_direction(&weak_bullet, pos, spawner.dir)
-            });
-            bullet.add_component(|| Movable::new(&weak_bullet, 300.));
-            bullet.add_component(|| {
-                Collider2d::with_ignore(
-                    &weak_bullet,
-                    Bounds::with_center_position(pos.x, pos.y, 10., 10.),
-                    vec![spawner.owner_id.clone()],
-                )
-            });
-            bullet.add_component(|| Sprite::new(&weak_bullet, 10., 10., "tank1.png"));
-            bullet.add_component(|| Damagable::new(&weak_bullet, 1));
-            bullet.add_component(|| Lifetime::new(&weak_bullet, 5.));
+        for (id, (mut gun, transform)) in ecs.query::<GunQuery>().iter_with_id() {
+            gun.update_timer(delta);
+            if let Some(spawner) = gun.consume_spawner(id, transform) {
+                let pos = spawner.pos + spawner.dir * 30.;
+                let params = SpawnParams::with_owner(pos, spawner.dir, spawner.owner_id);
+                let bullet = spawner.spawn_bullet();
+                commands.add(move |world| Self::spawn_bullet(world, &spawner.prefab, &params, bullet));
+            }
         }
     }
 
     fn apply_damage(&self, world: &EcsRc) {
         let ecs = world.borrow();
-        static mut DAMAGE_TARGETS : Vec<(u32, EntityId)> = Vec::new();
-        ecs.visit_all::<Bullet>(|bullet|{
-            
-            if let Some(target) = bullet.consume_target() {
-                unsafe { DAMAGE_TARGETS.push((bullet.get_damage(), target)) };
-                bullet.entity.upgrade().unwrap().kill();
-            }
-        });
+        let mut commands = ecs.commands();
 
-        unsafe {
-            while let Some(info) = DAMAGE_TARGETS.pop() {
-                ecs.visit::<Damagable>(info.1, |damagable| {
-                    if let Some(damagable) = damagable {
-                        damagable.do_damage(info.0);
+        for (id, mut bullet) in ecs.query::<&mut Bullet>().iter_with_id() {
+            if let Some(target) = bullet.consume_target() {
+                let damage = bullet.get_damage();
+                let owner = bullet.get_owner();
+                commands.add(move |world| {
+                    let ecs = world.borrow();
+                    let is_dead = ecs.query::<&mut Damagable>().get(target).is_some_and(|mut damagable| {
+                        damagable.do_damage(damage);
+                        damagable.is_dead()
+                    });
+                    if is_dead {
+                        ecs.resource_mut::<Score>().unwrap().add_kill(owner);
+                        ecs.remove_entity(target);
                     }
                 });
+                commands.despawn_pooled::<Bullet>(id);
             }
         }
     }
@@ -85,12 +87,20 @@ impl FireSystem {
 
 
 impl System for FireSystem {
-    fn update(&mut self, world: &EcsRc, delta: f32) {
-        
+    fn access(&self) -> Option<Access> {
+        Some(
+            Access::new()
+                .query::<GunQuery>()
+                .query::<&mut Bullet>()
+                .resource::<GameClock>(),
+        )
+    }
+
+    fn update(&mut self, world: &EcsRc) {
+        let delta = world.borrow().resource::<GameClock>().unwrap().get_delta();
+
         self.update_guns(world, delta);
-        
-        self.spawn_bullets(world);
 
         self.apply_damage(world);
     }
-}
\ No newline at end of file
+}
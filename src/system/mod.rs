@@ -1,4 +1,5 @@
-pub mod system_trait;
 pub mod fire_system;
 pub mod ai_system;
-pub mod move_system;
\ No newline at end of file
+pub mod move_system;
+pub mod bullet_system;
+pub mod transform_system;
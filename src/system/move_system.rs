@@ -1,58 +1,70 @@
-use std::{cell::RefCell, rc::Rc};
-use super::system_trait::System;
+use ecs::{Access, Changed, System};
 use crate::{
     bounds::Bounds, 
     collider2d::{Collider2d, CollisionEvent}, 
     object_components::{Bullet, BulletSpawner, Damagable, Gun, Lifetime, Movable}, 
     player_config::{PlayerController, PlayerState}, 
     quad_tree::QuadTree, 
+    resources::GameClock,
     sprite::Sprite, 
     transform::Transform
 };
-pub struct MoveSystem {
-    quad_tree: Rc<RefCell<QuadTree>>,
-}
+pub struct MoveSystem {}
 
 impl MoveSystem {
-    pub fn new(quad_tree: Rc<RefCell<QuadTree>>) -> Self {
-        Self { quad_tree }
+    pub fn new() -> Self {
+        Self {}
     }
 }
 
+type ControlledQuery<'a> = (&'a mut Transform, &'a PlayerController, &'a mut Movable);
+type MovedQuery<'a> = (&'a mut Transform, &'a Movable);
+
 impl System for MoveSystem {
-    fn update(&mut self, world: &ecs::EcsRc, delta: f32) {
+    fn access(&self) -> Option<Access> {
+        Some(
+            Access::new()
+                .query::<ControlledQuery>()
+                .query_filtered::<MovedQuery, Changed<Movable>>()
+                // Moving updates the collider bounds in the quad tree.
+                .write::<Collider2d>()
+                .resource::<GameClock>()
+                .resource_mut::<QuadTree>(),
+        )
+    }
+
+    fn update(&mut self, world: &ecs::EcsRc) {
         let ecs = world.borrow();
+        let delta = ecs.resource::<GameClock>().unwrap().get_delta();
+        let quad_tree = ecs.resource::<QuadTree>().unwrap();
 
-        ecs.visit_all3::<Transform, PlayerController, Movable>(|transform, controller, movable| {
-            
-            movable.set_dirty(controller.state == PlayerState::Move);
+        let mut controlled = ecs.query::<ControlledQuery>();
+        for (mut transform, controller, mut movable) in controlled.iter_mut() {
+            if controller.state == PlayerState::Move {
+                movable.set_changed();
+            }
 
             let direction = controller.direction;
 
             if !transform.get_direction().eq(&direction) {
                 transform.set_direction(&direction);
             }
-        });
-
-        ecs.visit_all2::<Transform, Movable>(|transform, movable| {
-            if movable.is_dirty() {
-                let pos = transform.get_position();
-                let speed = movable.get_speed() * delta;
-                let dir = transform.get_direction();
-                let new_pos = pos + dir * speed;
-                if let Some(entity) = transform.entity.upgrade() {
-                    let mut quad_tree = self.quad_tree.borrow_mut();
-                    let summary = quad_tree.move_object(&ecs, &entity, new_pos);
-                    if summary.can_move {
-                        transform.set_position(&new_pos);
-                    } else if let Some(collide_ent) = summary.collide_ent {
-                        //println!("Cant move help!!! {:?}", new_pos);
-                        entity.push_event(CollisionEvent::OnEntity(collide_ent));
-                    }
-                }
-
-                movable.set_dirty(false);
+        }
+        drop(controlled);
+
+        let mut moved = ecs.query_filtered::<MovedQuery, Changed<Movable>>();
+        for (id, (mut transform, movable)) in moved.iter_with_id() {
+            let pos = transform.get_position();
+            let speed = movable.get_speed() * delta;
+            let dir = transform.get_direction();
+            let new_pos = pos + dir * speed;
+            let summary = quad_tree.move_object(&ecs, id, new_pos);
+            if summary.can_move {
+                transform.set_position(&new_pos);
+            } else if let Some(collide_ent) = summary.collide_ent {
+                //println!("Cant move help!!! {:?}", new_pos);
+                ecs.send_entity_event(id, CollisionEvent::OnEntity(collide_ent));
             }
-        });
+        }
     }
-}
\ No newline at end of file
+}
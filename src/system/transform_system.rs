@@ -0,0 +1,93 @@
+use ecs::{Access, Added, Changed, Children, Ecs, EcsRc, EntityId, Parent, System, With};
+use fxhash::FxHashSet;
+
+use crate::transform::{GlobalTransform, Transform};
+
+pub struct TransformSystem {
+    // Entities that had a parent on the last run, the ones losing it become
+    // roots and need new globals.
+    parented: FxHashSet<EntityId>,
+}
+
+impl TransformSystem {
+    pub fn new() -> Self {
+        Self {
+            parented: FxHashSet::default(),
+        }
+    }
+
+    fn propagate(ecs: &Ecs, entity_id: EntityId, matrix: glm::Mat4) {
+        if let Some(mut global) = ecs.get_mut::<GlobalTransform>(entity_id) {
+            if *global.get_matrix() != matrix {
+                global.set_matrix(&matrix);
+            }
+        }
+
+        for child in ecs.get_children(entity_id) {
+            let local = match ecs.get::<Transform>(child) {
+                Some(transform) => transform.transform,
+                None => continue,
+            };
+            Self::propagate(ecs, child, matrix * local);
+        }
+    }
+
+    fn has_dirty_ancestor(ecs: &Ecs, entity_id: EntityId, dirty: &FxHashSet<EntityId>) -> bool {
+        let mut current = ecs.get_parent(entity_id);
+        while let Some(id) = current {
+            if dirty.contains(&id) {
+                return true;
+            }
+            current = ecs.get_parent(id);
+        }
+        false
+    }
+}
+
+impl System for TransformSystem {
+    fn access(&self) -> Option<Access> {
+        Some(
+            Access::new()
+                .query_filtered::<&mut Transform, Changed<Transform>>()
+                .query::<&mut GlobalTransform>()
+                .query::<(&Parent, &Children)>(),
+        )
+    }
+
+    fn update(&mut self, world: &EcsRc) {
+        let ecs = world.borrow();
+
+        let mut dirty = FxHashSet::default();
+        for (id, mut transform) in ecs.query_filtered::<&mut Transform, Changed<Transform>>().iter_with_id() {
+            transform.apply_transform_changes();
+            dirty.insert(id);
+        }
+        dirty.extend(ecs.query_filtered::<EntityId, Added<Parent>>().iter_mut());
+
+        let parented: FxHashSet<EntityId> = ecs.query_filtered::<EntityId, With<Parent>>().iter_mut().collect();
+        dirty.extend(
+            self.parented
+                .difference(&parented)
+                .filter(|id| ecs.is_entity_alive(**id)),
+        );
+        self.parented = parented;
+
+        // Only subtrees under a changed root are rebuilt, everything else
+        // keeps the globals of the last run.
+        for &id in dirty.iter() {
+            if Self::has_dirty_ancestor(&ecs, id, &dirty) {
+                continue;
+            }
+
+            let local = match ecs.get::<Transform>(id) {
+                Some(transform) => transform.transform,
+                None => continue,
+            };
+            let parent = ecs
+                .get_parent(id)
+                .and_then(|parent| ecs.get::<GlobalTransform>(parent).map(|global| *global.get_matrix()))
+                .unwrap_or_else(glm::Mat4::identity);
+            Self::propagate(&ecs, id, parent * local);
+        }
+    }
+}
@@ -1,6 +1,5 @@
 extern crate nalgebra_glm as glm;
 use ::ecs::*;
-use ecs_derive::component_impl;
 
 use glm::vec2;
 
@@ -10,25 +9,21 @@ pub enum TransformProp {
     Rotation(f32),
 }
 
-#[component_impl]
-#[derive(Debug, Clone, Default)]
+#[derive(Component, Debug, Clone, Default)]
 pub struct Transform {
     rotation: f32,
     position: glm::Vec2,
     direction: glm::Vec2,
     pub transform: glm::Mat4,
-    pub transform_changed: bool,
 }
 
 impl Transform {
-    pub fn new(entity: &EntityWeak, position: glm::Vec2) -> Self {
+    pub fn new(position: glm::Vec2) -> Self {
         let mut result = Self {
-            entity: entity.clone(),
             rotation: 0.,
             position,
             direction: vec2(0., 1.),
             transform: glm::identity(),
-            transform_changed: true,
         };
 
         result.apply_transform_changes();
@@ -36,14 +31,12 @@ impl Transform {
         result
     }
 
-    pub fn with_direction(entity: &EntityWeak, position: glm::Vec2, dir: glm::Vec2) -> Self {
+    pub fn with_direction(position: glm::Vec2, dir: glm::Vec2) -> Self {
         let mut result = Self {
-            entity: entity.clone(),
             rotation: 0.,
             position,
             direction: dir,
             transform: glm::identity(),
-            transform_changed: true,
         };
 
         result.apply_transform_changes();
@@ -73,7 +66,6 @@ impl Transform {
 
     pub fn set_position(&mut self, pos: &glm::Vec2) {
         self.position = *pos;
-        self.transform_changed = true;
     }
 
     pub fn get_rotation(&self) -> f32 {
@@ -82,12 +74,10 @@ impl Transform {
 
     pub fn set_rotation(&mut self, rotation: f32) {
         self.rotation = rotation;
-        self.transform_changed = true;
     }
 
     pub fn set_direction(&mut self, dir: &glm::Vec2) {
         self.direction = vec2(dir.x, dir.y);
-        self.transform_changed = true;
     }
 
     pub fn get_direction(&self) -> glm::Vec2 {
@@ -95,21 +85,71 @@ impl Transform {
     }
 
     pub fn apply_transform_changes(&mut self) {
-        if self.transform_changed {
-            let def_up = glm::vec3::<f32>(0., 1., 0.);
-            let def_left = glm::vec3::<f32>(1., 0., 0.);
-            let dir = glm::vec3(self.direction.x, self.direction.y, 0.);
-            let x_ang = glm::angle::<f32, 3>(&def_left, &dir);
-            self.rotation =
-                glm::angle::<f32, 3>(&def_up, &dir) * (if x_ang <= 0. { -1. } else { 1. });
+        let def_up = glm::vec3::<f32>(0., 1., 0.);
+        let def_left = glm::vec3::<f32>(1., 0., 0.);
+        let dir = glm::vec3(self.direction.x, self.direction.y, 0.);
+        let x_ang = glm::angle::<f32, 3>(&def_left, &dir);
+        self.rotation =
+            glm::angle::<f32, 3>(&def_up, &dir) * (if x_ang <= 0. { -1. } else { 1. });
+
+        //println!("rotation {}, x_ang {}", self.rotation, x_ang);
+        let pos = glm::vec3(self.position.x, self.position.y, 0.);
+
+        self.transform =
+            glm::translation(&pos) * glm::rotation(self.rotation, &glm::vec3(0., 0., 1.));
+    }
+}
+
+impl Serializable for Transform {
+    const NAME: &'static str = "transform";
 
-            //println!("rotation {}, x_ang {}", self.rotation, x_ang);
-            let pos = glm::vec3(self.position.x, self.position.y, 0.);
+    fn save(&self, writer: &mut SnapshotWriter) {
+        writer.write_f32(self.position.x);
+        writer.write_f32(self.position.y);
+        writer.write_f32(self.direction.x);
+        writer.write_f32(self.direction.y);
+    }
 
-            self.transform =
-                glm::translation(&pos) * glm::rotation(self.rotation, &glm::vec3(0., 0., 1.));
+    fn load(reader: &mut SnapshotReader) -> Option<Self> {
+        let position = vec2(reader.read_f32()?, reader.read_f32()?);
+        let direction = vec2(reader.read_f32()?, reader.read_f32()?);
 
-            self.transform_changed = false;
+        Some(Self::with_direction(position, direction))
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct GlobalTransform {
+    matrix: glm::Mat4,
+}
+
+impl GlobalTransform {
+    pub fn new() -> Self {
+        Self {
+            matrix: glm::identity(),
         }
     }
+
+    pub fn get_matrix(&self) -> &glm::Mat4 {
+        &self.matrix
+    }
+
+    pub fn set_matrix(&mut self, matrix: &glm::Mat4) {
+        self.matrix = *matrix;
+    }
+
+    pub fn get_position(&self) -> glm::Vec2 {
+        vec2(self.matrix[(0, 3)], self.matrix[(1, 3)])
+    }
+}
+
+// The matrix is recomputed by the transform system, only presence is saved.
+impl Serializable for GlobalTransform {
+    const NAME: &'static str = "global_transform";
+
+    fn save(&self, _writer: &mut SnapshotWriter) {}
+
+    fn load(_reader: &mut SnapshotReader) -> Option<Self> {
+        Some(Self::new())
+    }
 }